@@ -0,0 +1,787 @@
+use crate::models::{ApiChange, ApiChangeKind, ApiItem, ApiSnapshot, CodeFile, ItemKind};
+
+use anyhow::{Error, Result};
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::LazyLock,
+};
+use syn::{__private::ToTokens, Item};
+
+// Trailing commas only depend on formatting.
+static TRAILING_COMMA: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" ,(\s*[)\]}>])").unwrap());
+
+struct CollectedItem {
+    api_item: ApiItem,
+    reachable: bool,
+    // Path of the item where it is declared, that of the item itself unless re-exported.
+    origin: String,
+}
+
+// `pub use` path of a module, resolved once all the modules and items are collected.
+struct UsePath {
+    module_path: String,
+    reachable: bool,
+    segments: Vec<String>,
+    name: Option<String>,
+}
+
+struct ReExport {
+    module_path: String,
+    reachable: bool,
+    target: String,
+    // None for glob imports.
+    name: Option<String>,
+}
+
+// Impl block whose items are part of the API wherever its type is, resolved once the re-exports
+// of the type are.
+struct CollectedImpl {
+    module_path: String,
+    self_type: Vec<String>,
+    members: Vec<ImplMember>,
+}
+
+enum ImplMember {
+    // The path of the trait and the header of the block.
+    Trait(String, String),
+    // The ident and the signature of a public method.
+    Fn(String, String),
+}
+
+#[derive(Default)]
+struct ApiCollector {
+    items: BTreeMap<String, CollectedItem>,
+    impls: Vec<CollectedImpl>,
+    module_paths: BTreeSet<String>,
+    module_sources: BTreeMap<String, syn::File>,
+    re_exports: Vec<ReExport>,
+    use_paths: Vec<UsePath>,
+}
+
+const TYPE_KINDS: [ItemKind; 4] = [
+    ItemKind::Enum,
+    ItemKind::Struct,
+    ItemKind::Type,
+    ItemKind::Union,
+];
+
+// The public API is computed from the source of the modules of the parsed repository, starting
+// from the crate root (lib.rs) and following public modules and `pub use` re-exports.
+pub fn api_snapshot<P: AsRef<std::path::Path>>(code_files: &[CodeFile<P>]) -> Result<ApiSnapshot> {
+    let mut collector = ApiCollector::default();
+
+    for code_file in code_files {
+        // The module representing the file is always the last element of a CodeFile.
+        if let Some(code_element) = code_file
+            .elements
+            .last()
+            .filter(|code_element| code_element.code_element_id.kind == ItemKind::Mod)
+        {
+            if code_element.code.is_empty() {
+                return Err(Error::msg(format!(
+                    "Missing source code for module {}",
+                    code_element.code_element_id.location
+                )));
+            }
+
+            let module_path = if code_element.code_element_id.location == "crate :: lib" {
+                "crate".to_string()
+            } else {
+                code_element.code_element_id.location.clone()
+            };
+
+            collector
+                .module_sources
+                .insert(module_path, syn::parse_file(&code_element.code)?);
+        }
+    }
+
+    collector.module_paths = collector.module_sources.keys().cloned().collect();
+
+    let Some(root) = collector.module_sources.remove("crate") else {
+        return Err(Error::msg("The public API requires a lib.rs crate root"));
+    };
+
+    collector.collect_items("crate", &root.items, true);
+    for use_path in std::mem::take(&mut collector.use_paths) {
+        collector.collect_re_export(use_path);
+    }
+    collector.resolve_re_exports();
+    collector.resolve_impls();
+
+    let items = collector
+        .items
+        .into_values()
+        .filter(|collected_item| collected_item.reachable)
+        .map(|collected_item| collected_item.api_item)
+        .collect();
+
+    Ok(ApiSnapshot { items })
+}
+
+pub fn api_snapshot_to_text(api_snapshot: &ApiSnapshot) -> String {
+    api_snapshot
+        .items
+        .iter()
+        .map(|api_item| format!("{}: {}\n", api_item.path, api_item.signature))
+        .collect()
+}
+
+pub fn compare_api_snapshots(
+    old_snapshot: &ApiSnapshot,
+    new_snapshot: &ApiSnapshot,
+) -> Vec<ApiChange> {
+    let old_items: BTreeMap<&str, &ApiItem> = old_snapshot
+        .items
+        .iter()
+        .map(|api_item| (api_item.path.as_str(), api_item))
+        .collect();
+    let new_items: BTreeMap<&str, &ApiItem> = new_snapshot
+        .items
+        .iter()
+        .map(|api_item| (api_item.path.as_str(), api_item))
+        .collect();
+
+    let mut api_changes = Vec::new();
+
+    for (path, old_item) in &old_items {
+        match new_items.get(path) {
+            None => api_changes.push(ApiChange {
+                path: path.to_string(),
+                kind: ApiChangeKind::Removed,
+                breaking: true,
+                old_signature: Some(old_item.signature.clone()),
+                new_signature: None,
+            }),
+            Some(new_item) if new_item.signature != old_item.signature => {
+                api_changes.push(ApiChange {
+                    path: path.to_string(),
+                    kind: ApiChangeKind::SignatureChanged,
+                    breaking: true,
+                    old_signature: Some(old_item.signature.clone()),
+                    new_signature: Some(new_item.signature.clone()),
+                })
+            }
+            Some(_) => (),
+        }
+    }
+
+    for (path, new_item) in &new_items {
+        if old_items.contains_key(path) {
+            continue;
+        }
+
+        // Implementors of an existing trait break when a required item is added to it.
+        let required_in_existing_trait = new_item.required
+            && path
+                .rsplit_once(" :: ")
+                .is_some_and(|(trait_path, _)| old_items.contains_key(trait_path));
+
+        api_changes.push(ApiChange {
+            path: path.to_string(),
+            kind: if required_in_existing_trait {
+                ApiChangeKind::RequiredTraitItemAdded
+            } else {
+                ApiChangeKind::Added
+            },
+            breaking: required_in_existing_trait,
+            old_signature: None,
+            new_signature: Some(new_item.signature.clone()),
+        });
+    }
+
+    api_changes.sort_by(|a, b| b.breaking.cmp(&a.breaking).then(a.path.cmp(&b.path)));
+    api_changes
+}
+
+pub fn api_changes_to_markdown(api_changes: &[ApiChange]) -> String {
+    let mut markdown = String::from("# Public API changes\n");
+
+    for (title, breaking) in [("Breaking changes", true), ("Other changes", false)] {
+        let section: Vec<&ApiChange> = api_changes
+            .iter()
+            .filter(|api_change| api_change.breaking == breaking)
+            .collect();
+
+        if section.is_empty() {
+            continue;
+        }
+
+        markdown += &format!("\n## {title}\n\n");
+        for api_change in section {
+            markdown += &match (&api_change.old_signature, &api_change.new_signature) {
+                (Some(old_signature), Some(new_signature)) => format!(
+                    "- {} `{}`: `{old_signature}` -> `{new_signature}`\n",
+                    api_change.kind, api_change.path
+                ),
+                (Some(signature), None) | (None, Some(signature)) => format!(
+                    "- {} `{}`: `{signature}`\n",
+                    api_change.kind, api_change.path
+                ),
+                (None, None) => format!("- {} `{}`\n", api_change.kind, api_change.path),
+            };
+        }
+    }
+
+    markdown
+}
+
+impl ApiCollector {
+    fn collect_items(&mut self, module_path: &str, items: &[Item], reachable: bool) {
+        for item in items {
+            match item {
+                Item::Impl(item_impl) => self.collect_impl(module_path, item_impl),
+                Item::Macro(item_macro) => {
+                    // Exported macros always live at the crate root.
+                    if let Some(ident) = &item_macro.ident {
+                        if item_macro
+                            .attrs
+                            .iter()
+                            .any(|attr| attr.path().is_ident("macro_export"))
+                        {
+                            self.insert(
+                                ApiItem::new(
+                                    format!("crate :: {ident}"),
+                                    ItemKind::Macro,
+                                    format!("macro_rules! {ident}"),
+                                ),
+                                true,
+                            );
+                        }
+                    }
+                }
+                Item::Mod(item_mod) => {
+                    let child_path = format!("{module_path} :: {}", item_mod.ident);
+                    let child_reachable = reachable && is_public(&item_mod.vis);
+                    self.module_paths.insert(child_path.clone());
+
+                    if let Some((_, nested_items)) = &item_mod.content {
+                        self.collect_items(&child_path, nested_items, child_reachable);
+                    } else if let Some(file) = self.module_sources.remove(&child_path) {
+                        self.collect_items(&child_path, &file.items, child_reachable);
+                    }
+                }
+                Item::Use(item_use) if is_public(&item_use.vis) => {
+                    let mut use_paths = Vec::new();
+                    explode_use_tree(&item_use.tree, Vec::new(), &mut use_paths);
+
+                    // Modules and items declared later in the file are not collected yet.
+                    self.use_paths
+                        .extend(use_paths.into_iter().map(|(segments, name)| UsePath {
+                            module_path: module_path.to_string(),
+                            reachable,
+                            segments,
+                            name,
+                        }));
+                }
+                _ => {
+                    if let Some((ident, api_item)) = item_signature(item) {
+                        let path = format!("{module_path} :: {ident}");
+
+                        if let Item::Trait(item_trait) = item {
+                            for trait_item in &item_trait.items {
+                                if let Some((ident, kind, signature, required)) =
+                                    trait_item_signature(trait_item)
+                                {
+                                    let mut api_item =
+                                        ApiItem::new(format!("{path} :: {ident}"), kind, signature);
+                                    api_item.required = required;
+                                    self.insert(api_item, reachable);
+                                }
+                            }
+                        }
+
+                        self.insert(ApiItem { path, ..api_item }, reachable);
+                    }
+                }
+            }
+        }
+    }
+
+    // Blocks can be declared in any module, even a private one, their items being reachable
+    // through the type.
+    fn collect_impl(&mut self, module_path: &str, item_impl: &syn::ItemImpl) {
+        let syn::Type::Path(type_path) = item_impl.self_ty.as_ref() else {
+            return;
+        };
+
+        let members = match &item_impl.trait_ {
+            Some((_, trait_path, _)) => {
+                let mut header = item_impl.clone();
+                header.attrs.clear();
+                header.items.clear();
+
+                vec![ImplMember::Trait(
+                    trait_path.to_token_stream().to_string(),
+                    header.to_token_stream().to_string(),
+                )]
+            }
+            None => item_impl
+                .items
+                .iter()
+                .filter_map(|impl_item| match impl_item {
+                    syn::ImplItem::Fn(impl_item_fn) if is_public(&impl_item_fn.vis) => {
+                        Some(ImplMember::Fn(
+                            impl_item_fn.sig.ident.to_string(),
+                            impl_item_fn.sig.to_token_stream().to_string(),
+                        ))
+                    }
+                    _ => None,
+                })
+                .collect(),
+        };
+
+        self.impls.push(CollectedImpl {
+            module_path: module_path.to_string(),
+            self_type: type_path
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect(),
+            members,
+        });
+    }
+
+    // Items of impl blocks are added at each public path of their type, e.g. `crate :: Foo ::
+    // new` and `crate :: impl Display for Foo` for a type re-exported by the crate root.
+    fn resolve_impls(&mut self) {
+        for collected_impl in std::mem::take(&mut self.impls) {
+            let origins = self.type_origins(&collected_impl.module_path, &collected_impl.self_type);
+            let type_paths: Vec<String> = self
+                .items
+                .values()
+                .filter(|collected_item| {
+                    collected_item.reachable
+                        && TYPE_KINDS.contains(&collected_item.api_item.kind)
+                        && origins.contains(&collected_item.origin)
+                })
+                .map(|collected_item| collected_item.api_item.path.clone())
+                .collect();
+
+            for type_path in type_paths {
+                let Some((parent_path, type_name)) = type_path.rsplit_once(" :: ") else {
+                    continue;
+                };
+
+                for member in &collected_impl.members {
+                    let mut api_item = match member {
+                        ImplMember::Trait(trait_path, header) => ApiItem::new(
+                            format!("{parent_path} :: impl {trait_path} for {type_name}"),
+                            ItemKind::Impl,
+                            header.clone(),
+                        ),
+                        ImplMember::Fn(ident, signature) => ApiItem::new(
+                            format!("{type_path} :: {ident}"),
+                            ItemKind::Fn,
+                            signature.clone(),
+                        ),
+                    };
+                    api_item.self_type = Some(type_name.to_string());
+                    self.insert(api_item, true);
+                }
+            }
+        }
+    }
+
+    // Declaration paths of the type an impl block is for. Types imported by a private `use` are
+    // not resolved, the types of the crate with the same name are taken instead.
+    fn type_origins(&self, module_path: &str, self_type: &[String]) -> BTreeSet<String> {
+        let mut base: Vec<&str> = module_path.split(" :: ").collect();
+        let mut segments = self_type;
+
+        match segments.first().map(String::as_str) {
+            Some("crate") => base.clear(),
+            Some("self") => segments = &segments[1..],
+            Some("super") => {
+                while segments.first().is_some_and(|segment| segment == "super") {
+                    segments = &segments[1..];
+                    base.pop();
+                }
+            }
+            _ => (),
+        }
+        base.extend(segments.iter().map(String::as_str));
+        let path = base.join(" :: ");
+
+        let declared_types: Vec<&String> = self
+            .items
+            .values()
+            .filter(|collected_item| TYPE_KINDS.contains(&collected_item.api_item.kind))
+            .map(|collected_item| &collected_item.origin)
+            .collect();
+        if declared_types.contains(&&path) {
+            return BTreeSet::from([path]);
+        }
+
+        let Some(type_name) = self_type.last() else {
+            return BTreeSet::new();
+        };
+        declared_types
+            .into_iter()
+            .filter(|origin| origin.rsplit(" :: ").next() == Some(type_name.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    fn collect_re_export(&mut self, use_path: UsePath) {
+        let UsePath {
+            module_path,
+            reachable,
+            mut segments,
+            name,
+        } = use_path;
+        let module_path = module_path.as_str();
+        let mut base: Vec<String> = module_path.split(" :: ").map(String::from).collect();
+
+        match segments.first().map(String::as_str) {
+            Some("crate") => base.clear(),
+            Some("self") => {
+                segments.remove(0);
+            }
+            Some("super") => {
+                while segments.first().is_some_and(|segment| segment == "super") {
+                    segments.remove(0);
+                    base.pop();
+                }
+            }
+            Some(first) => {
+                let child_path = format!("{module_path} :: {first}");
+
+                // Neither a child module nor an item of this crate: an external re-export.
+                if !self.module_paths.contains(&child_path) && !self.items.contains_key(&child_path)
+                {
+                    let signature = format!(
+                        "pub use {}{}",
+                        segments.join(" :: "),
+                        if name.is_none() { " :: *" } else { "" }
+                    );
+                    let ident = name.unwrap_or_else(|| format!("{} :: *", segments.join(" :: ")));
+
+                    self.insert(
+                        ApiItem::new(
+                            format!("{module_path} :: {ident}"),
+                            ItemKind::Use,
+                            signature,
+                        ),
+                        reachable,
+                    );
+                    return;
+                }
+            }
+            None => return,
+        }
+
+        base.extend(segments);
+
+        self.re_exports.push(ReExport {
+            module_path: module_path.to_string(),
+            reachable,
+            target: base.join(" :: "),
+            name,
+        });
+    }
+
+    fn insert(&mut self, api_item: ApiItem, reachable: bool) -> bool {
+        let origin = api_item.path.clone();
+        self.insert_from(api_item, origin, reachable)
+    }
+
+    fn insert_from(&mut self, api_item: ApiItem, origin: String, reachable: bool) -> bool {
+        match self.items.get_mut(&api_item.path) {
+            Some(collected_item) if !collected_item.reachable && reachable => {
+                collected_item.reachable = true;
+                true
+            }
+            Some(_) => false,
+            None => {
+                self.items.insert(
+                    api_item.path.clone(),
+                    CollectedItem {
+                        api_item,
+                        reachable,
+                        origin,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    // Re-exports can point to other re-exports, they are resolved until nothing changes, chains
+    // of re-exports being at most as long as their number.
+    //
+    // Globs re-exporting an ancestor, e.g. `pub use super::*;`, would re-export the module into
+    // itself endlessly: they skip the items of the module, and the items other such globs
+    // re-exported.
+    fn resolve_re_exports(&mut self) {
+        let re_exports = std::mem::take(&mut self.re_exports);
+        let mut ancestor_glob_paths: BTreeSet<String> = BTreeSet::new();
+        let mut changed = true;
+        let mut passes = 0;
+
+        while changed && passes <= re_exports.len() {
+            changed = false;
+            passes += 1;
+
+            for re_export in &re_exports {
+                let prefix = format!("{} :: ", re_export.target);
+                let module_prefix = format!("{} :: ", re_export.module_path);
+                let ancestor_glob =
+                    re_export.name.is_none() && re_export.module_path.starts_with(&prefix);
+
+                let new_items: Vec<(ApiItem, String)> = self
+                    .items
+                    .values()
+                    .filter_map(|collected_item| {
+                        let path = &collected_item.api_item.path;
+                        if ancestor_glob
+                            && (path.starts_with(&module_prefix)
+                                || ancestor_glob_paths.contains(path))
+                        {
+                            return None;
+                        }
+
+                        let new_path = match &re_export.name {
+                            Some(name) if *path == re_export.target => {
+                                format!("{} :: {name}", re_export.module_path)
+                            }
+                            Some(name) => path.strip_prefix(&prefix).map(|rest| {
+                                format!("{} :: {name} :: {rest}", re_export.module_path)
+                            })?,
+                            None => path
+                                .strip_prefix(&prefix)
+                                .map(|rest| format!("{} :: {rest}", re_export.module_path))?,
+                        };
+
+                        Some((
+                            ApiItem {
+                                path: new_path,
+                                ..collected_item.api_item.clone()
+                            },
+                            collected_item.origin.clone(),
+                        ))
+                    })
+                    .collect();
+
+                for (api_item, origin) in new_items {
+                    if ancestor_glob && !self.items.contains_key(&api_item.path) {
+                        ancestor_glob_paths.insert(api_item.path.clone());
+                    }
+                    changed |= self.insert_from(api_item, origin, re_export.reachable);
+                }
+            }
+        }
+    }
+}
+
+impl ApiItem {
+    fn new(path: String, kind: ItemKind, signature: String) -> Self {
+        Self {
+            path,
+            kind,
+            signature: TRAILING_COMMA.replace_all(&signature, "$1").into_owned(),
+            required: false,
+            self_type: None,
+        }
+    }
+}
+
+fn explode_use_tree(
+    use_tree: &syn::UseTree,
+    mut segments: Vec<String>,
+    use_paths: &mut Vec<(Vec<String>, Option<String>)>,
+) {
+    match use_tree {
+        syn::UseTree::Path(use_path) => {
+            segments.push(use_path.ident.to_string());
+            explode_use_tree(&use_path.tree, segments, use_paths);
+        }
+        syn::UseTree::Name(use_name) => {
+            let name = use_name.ident.to_string();
+
+            // `use a::b::{self}` re-exports the module b itself.
+            if name == "self" {
+                let name = segments.last().cloned();
+                use_paths.push((segments, name));
+            } else {
+                segments.push(name.clone());
+                use_paths.push((segments, Some(name)));
+            }
+        }
+        syn::UseTree::Rename(use_rename) => {
+            segments.push(use_rename.ident.to_string());
+            use_paths.push((segments, Some(use_rename.rename.to_string())));
+        }
+        syn::UseTree::Glob(_) => use_paths.push((segments, None)),
+        syn::UseTree::Group(use_group) => {
+            for tree in &use_group.items {
+                explode_use_tree(tree, segments.clone(), use_paths);
+            }
+        }
+    }
+}
+
+fn is_public(visibility: &syn::Visibility) -> bool {
+    matches!(visibility, syn::Visibility::Public(_))
+}
+
+// Attributes and bodies are not part of the signature, nor are private fields.
+fn item_signature(item: &Item) -> Option<(String, ApiItem)> {
+    let (ident, kind, signature) = match item {
+        Item::Const(item_const) if is_public(&item_const.vis) => (
+            item_const.ident.to_string(),
+            ItemKind::Const,
+            format!(
+                "const {} : {}",
+                item_const.ident,
+                item_const.ty.to_token_stream()
+            ),
+        ),
+        Item::Enum(item_enum) if is_public(&item_enum.vis) => {
+            let mut item_enum = item_enum.clone();
+            item_enum.attrs.clear();
+            for variant in &mut item_enum.variants {
+                variant.attrs.clear();
+                variant
+                    .fields
+                    .iter_mut()
+                    .for_each(|field| field.attrs.clear());
+            }
+            (
+                item_enum.ident.to_string(),
+                ItemKind::Enum,
+                item_enum.to_token_stream().to_string(),
+            )
+        }
+        Item::Fn(item_fn) if is_public(&item_fn.vis) => (
+            item_fn.sig.ident.to_string(),
+            ItemKind::Fn,
+            item_fn.sig.to_token_stream().to_string(),
+        ),
+        Item::Static(item_static) if is_public(&item_static.vis) => (
+            item_static.ident.to_string(),
+            ItemKind::Static,
+            format!(
+                "static {}{} : {}",
+                if matches!(item_static.mutability, syn::StaticMutability::Mut(_)) {
+                    "mut "
+                } else {
+                    ""
+                },
+                item_static.ident,
+                item_static.ty.to_token_stream()
+            ),
+        ),
+        Item::Struct(item_struct) if is_public(&item_struct.vis) => {
+            let mut item_struct = item_struct.clone();
+            item_struct.attrs.clear();
+            strip_private_fields(&mut item_struct.fields);
+            (
+                item_struct.ident.to_string(),
+                ItemKind::Struct,
+                item_struct.to_token_stream().to_string(),
+            )
+        }
+        Item::Trait(item_trait) if is_public(&item_trait.vis) => {
+            let mut item_trait = item_trait.clone();
+            item_trait.attrs.clear();
+            item_trait.items.clear();
+            (
+                item_trait.ident.to_string(),
+                ItemKind::Trait,
+                item_trait.to_token_stream().to_string(),
+            )
+        }
+        Item::TraitAlias(item_trait_alias) if is_public(&item_trait_alias.vis) => {
+            let mut item_trait_alias = item_trait_alias.clone();
+            item_trait_alias.attrs.clear();
+            (
+                item_trait_alias.ident.to_string(),
+                ItemKind::TraitAlias,
+                item_trait_alias.to_token_stream().to_string(),
+            )
+        }
+        Item::Type(item_type) if is_public(&item_type.vis) => {
+            let mut item_type = item_type.clone();
+            item_type.attrs.clear();
+            (
+                item_type.ident.to_string(),
+                ItemKind::Type,
+                item_type.to_token_stream().to_string(),
+            )
+        }
+        Item::Union(item_union) if is_public(&item_union.vis) => {
+            let mut item_union = item_union.clone();
+            item_union.attrs.clear();
+            let mut fields = syn::Fields::Named(item_union.fields.clone());
+            strip_private_fields(&mut fields);
+            if let syn::Fields::Named(fields) = fields {
+                item_union.fields = fields;
+            }
+            (
+                item_union.ident.to_string(),
+                ItemKind::Union,
+                item_union.to_token_stream().to_string(),
+            )
+        }
+        _ => return None,
+    };
+
+    Some((ident, ApiItem::new(String::new(), kind, signature)))
+}
+
+fn strip_private_fields(fields: &mut syn::Fields) {
+    let (syn::Fields::Named(syn::FieldsNamed { named: fields, .. })
+    | syn::Fields::Unnamed(syn::FieldsUnnamed {
+        unnamed: fields, ..
+    })) = fields
+    else {
+        return;
+    };
+
+    *fields = std::mem::take(fields)
+        .into_iter()
+        .filter(|field| is_public(&field.vis))
+        .map(|mut field| {
+            field.attrs.clear();
+            field
+        })
+        .collect();
+}
+
+// Returns the ident, the kind, the signature and whether implementors must provide the item.
+fn trait_item_signature(trait_item: &syn::TraitItem) -> Option<(String, ItemKind, String, bool)> {
+    match trait_item {
+        syn::TraitItem::Const(trait_item_const) => Some((
+            trait_item_const.ident.to_string(),
+            ItemKind::Const,
+            format!(
+                "const {} : {}",
+                trait_item_const.ident,
+                trait_item_const.ty.to_token_stream()
+            ),
+            trait_item_const.default.is_none(),
+        )),
+        syn::TraitItem::Fn(trait_item_fn) => Some((
+            trait_item_fn.sig.ident.to_string(),
+            ItemKind::Fn,
+            trait_item_fn.sig.to_token_stream().to_string(),
+            trait_item_fn.default.is_none(),
+        )),
+        syn::TraitItem::Type(trait_item_type) => {
+            let mut trait_item_type = trait_item_type.clone();
+            trait_item_type.attrs.clear();
+            let required = trait_item_type.default.is_none();
+            trait_item_type.default = None;
+            Some((
+                trait_item_type.ident.to_string(),
+                ItemKind::Type,
+                trait_item_type.to_token_stream().to_string(),
+                required,
+            ))
+        }
+        _ => None,
+    }
+}
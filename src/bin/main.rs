@@ -10,9 +10,13 @@ use rocket::{
 };
 use rusty_docs::{
     doc_gen::document_repository,
-    models::{CodeFile, ItemKind, UserQuestionResponse},
+    jsonl::read_jsonl_to_code_files,
+    models::{
+        CodeFile, DocGenOptions, DocumentationEdit, ItemKind, ParsedRevision, PromptTemplates,
+        UserQuestionResponse,
+    },
     openai::ask_the_model,
-    parsing::parse_repository,
+    parsing::{parse_repository, parse_repository_at_revision},
     qdrant::embed_repository,
 };
 use std::path::{Path, PathBuf};
@@ -48,6 +52,8 @@ struct AskReq<'a> {
     qdrant_collection_name: &'a str,
     qdrant_url: &'a str,
     user_question: &'a str,
+    #[serde(default)]
+    prompt_templates: PromptTemplates,
 }
 
 #[derive(Deserialize)]
@@ -58,6 +64,8 @@ struct DocumentReq<'a> {
     repository_path: &'a str,
     write_inside_repository: bool,
     write_to_json_path: &'a str,
+    #[serde(default)]
+    options: DocGenOptions,
 }
 
 #[derive(Deserialize)]
@@ -74,18 +82,27 @@ struct EmbedReq<'a> {
 #[serde(crate = "rocket::serde")]
 struct ParseReq<'a> {
     repository_path: &'a str,
+    revision: Option<&'a str>,
     write_to_json_path: &'a str,
 }
 
 fn retrieve_code_files(repository_path: &str) -> Result<Vec<CodeFile<PathBuf>>, String> {
     if Path::new(repository_path)
         .extension()
-        .map_or(false, |ext| ext.eq_ignore_ascii_case("json"))
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jsonl"))
+    {
+        read_jsonl_to_code_files(repository_path).map_err(|e| e.to_string())
+    } else if Path::new(repository_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
     {
         match std::fs::read_to_string(repository_path) {
             Ok(file_str) => match serde_json::from_str(&file_str) {
                 Ok(code_files) => Ok(code_files),
-                Err(e) => Err(e.to_string()),
+                Err(e) => match serde_json::from_str::<ParsedRevision>(&file_str) {
+                    Ok(parsed_revision) => Ok(parsed_revision.code_files),
+                    Err(_) => Err(e.to_string()),
+                },
             },
             Err(e) => Err(e.to_string()),
         }
@@ -110,6 +127,7 @@ async fn ask<'a>(req: Json<AskReq<'a>>) -> Result<Json<UserQuestionResponse>, St
         req.qdrant_collection_name,
         req.qdrant_url,
         req.user_question,
+        &req.prompt_templates,
     )
     .await
     {
@@ -119,22 +137,24 @@ async fn ask<'a>(req: Json<AskReq<'a>>) -> Result<Json<UserQuestionResponse>, St
 }
 
 #[post("/document", format = "application/json", data = "<req>")]
-async fn document<'a>(req: Json<DocumentReq<'a>>) -> Result<String, String> {
+async fn document<'a>(
+    req: Json<DocumentReq<'a>>,
+) -> Result<Json<Vec<DocumentationEdit>>, String> {
     let code_files = retrieve_code_files(req.repository_path)?;
 
-    if let Err(e) = document_repository(
+    match document_repository(
         req.llm,
         code_files,
         &[ItemKind::All],
         req.openai_api_key,
+        &req.options,
         req.write_inside_repository,
         Some(req.write_to_json_path),
     )
     .await
     {
-        Err(e.to_string())
-    } else {
-        Ok("Repository documented successfully.".into())
+        Ok(documentation_edits) => Ok(Json(documentation_edits)),
+        Err(e) => Err(e.to_string()),
     }
 }
 
@@ -160,13 +180,21 @@ async fn embed<'a>(req: Json<EmbedReq<'a>>) -> Result<String, String> {
 
 #[post("/parse", format = "application/json", data = "<req>")]
 fn parse(req: Json<ParseReq>) -> Result<String, String> {
-    if let Err(e) = parse_repository(req.repository_path, Some(req.write_to_json_path)) {
+    let parsing_result = if let Some(revision) = req.revision {
+        parse_repository_at_revision(req.repository_path, revision, Some(req.write_to_json_path))
+            .map(|_| ())
+    } else {
+        parse_repository(req.repository_path, Some(req.write_to_json_path)).map(|_| ())
+    };
+
+    if let Err(e) = parsing_result {
         Err(e.to_string())
     } else {
         Ok("Repository parsed successfully.".into())
     }
 }
 
+#[allow(clippy::result_large_err)]
 #[rocket::main]
 async fn main() -> Result<(), rocket::Error> {
     let _rocket = rocket::build()
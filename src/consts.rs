@@ -1,15 +1,22 @@
+// Prompts sent back to the model for a generated example failing as a doctest, by default.
+pub(crate) const DOC_EXAMPLE_MAX_RETRIES: usize = 2;
+// Characters of the doctest output given to the model to fix an example.
+pub(crate) const DOC_EXAMPLE_MAX_ERROR_CHARS: usize = 4000;
 pub(crate) const OPENAI_API_CHAT_COMPLETION_URL: &str = "https://api.openai.com/v1/chat/completions";
 pub(crate) const OPENAI_API_EMBEDDING_URL: &str = "https://api.openai.com/v1/embeddings";
 pub(crate) const OPENAI_API_SEED: u8 = 42;
 pub(crate) const OPENAI_API_TOP_P: f32 = 0.05;
+// Completion tokens expected for a documentation request, counted by the rate limiter.
+pub(crate) const OPENAI_DOC_GENERATION_COMPLETION_TOKENS: u64 = 1000;
+// Documentation of contained or used items given in a documentation request, at most.
+pub(crate) const OPENAI_DOC_GENERATION_MAX_RELATED_ITEMS: usize = 30;
 pub(crate) const OPENAI_EMBEDDING_MODEL_MAX_TOKENS: u64 = 8191;
 pub(crate) const OPENAI_EMBEDDING_MAX_VECTOR_SIZE: usize = 1536;
 
 
 pub(crate) const SYSTEM_MSG_DOC_GENERATION: &str = 
 "
-    You are a technical writer and rockstar Rust developer responsible for documenting the codebase 
-    in the Engineering department of a blockchain software company.
+    You are a technical writer and rockstar Rust developer responsible for documenting a codebase.
 
     Document the code element provided by the user following Rust's documentation conventions.
 
@@ -25,14 +32,55 @@ pub(crate) const SYSTEM_MSG_DOC_GENERATION: &str =
 	    'error_possible': string  // True if kind == Function && the code can return an error, false otherwise
 	    'error_section': string  // If error_possible == true then a description of all errors possible. Insert references to other parts of code between backticks ``
 	    'example_section': string  // If kind == Function then include code examples using the function provided in the simplest way possible. The example provided should be a working one, therefore doctest must always succed
+	    'safety_section': string  // If the code is an unsafe fn or an unsafe trait then the invariants that callers or implementors must uphold, an empty string otherwise. Insert references to other parts of code between backticks ``
 	    'has_fields_or_variants': string  // True if kind == Struct || kind == Enum, false otherwise
-	    'fields_or_variants_descriptions': string  // A list of json objects containing the ident of and a description for each field or variant or an empty list. Insert references to other parts of code between backticks ``
+	    'fields_or_variants_descriptions': string  // A list of json objects containing the ident of and a description for each field or variant or an empty list. The fields of tuple structs have no ident, use their index instead, e.g. '0' for the first one. Insert references to other parts of code between backticks ``
     }
     
     Wrap your final output with closed and open brackets (a list of json objects).
 ";
 
+pub(crate) const SYSTEM_MSG_EXAMPLE_FIX: &str = 
+"
+    You are a rockstar Rust developer fixing the documentation examples of a codebase.
+
+    The user provides a code element, the example written in its documentation and the output of 
+    rustdoc when running the example as a doctest. Rewrite the example so that the doctest compiles 
+    and succeeds, using the code element in the simplest way possible. Items of the crate must be 
+    imported with their full path.
+
+    Follow exactly format instructions to produce the final output:
+    
+    json {
+	    'example_section': string  // The fixed example, with its code between ``` fences
+    }
+";
+
 pub(crate) const SYSTEM_MSG_USER_QUESTION: &str = 
+"
+    Follow exactly format instructions to produce the final output:
+    
+    json {
+	    'response': string  // This is your response to the question
+	    'suggested_questions': [string]  // These are three suggested questions so that the user can explore further the repository
+    }
+    
+    Wrap your final output with closed and open brackets (a list of json objects).
+";
+
+// Default templates of the prompts, see `PromptTemplates` for the placeholders.
+pub(crate) const TEMPLATE_DOC_GENERATION: &str = 
+"{project_description}
+
+{style_guide}
+
+Provide the documentation to insert directly in the code of {ident}, a Rust {kind} whose location is {location}:
+
+{code}
+
+{dependencies}";
+
+pub(crate) const TEMPLATE_USER_QUESTION: &str = 
 "
 		You are a seasoned Rust developer and expert who has extensively contributed to various Rust projects. As an ambassador of the Rust programming language, 
 		you have a deep understanding of its ecosystem, best practices, and community standards.
@@ -40,20 +88,13 @@ pub(crate) const SYSTEM_MSG_USER_QUESTION: &str =
 		You have recently performed a comprehensive Rust Analysis using RAG (Repository Analysis with Qdrant). The analysis included exploring code elements, 
 		identifying dependencies, and assessing the overall structure of a Rust repository.
 
+		{project_description}
+
 		Now, you are ready to answer questions related to the analyzed repository. Use the insights gathered from the RAG to provide informative and knowledgeable 
 		responses. Feel free to share your expertise on Rust conventions, code organization, potential improvements, and any other relevant insights.
 
 		Keep your answers clear, concise, and tailored to the specific context of the repository in question. Your goal is to assist and guide users based on your 
 		in-depth knowledge of Rust and the findings from the recent analysis.
 
-    Follow exactly format instructions to produce the final output:
-    
-    json {
-	    'response': string  // This is your response to the question
-	    'suggested_questions': [string]  // These are three suggested questions so that the user can explore further the repository
-    }
-    
-    Wrap your final output with closed and open brackets (a list of json objects).
-
 		Here is the data coming from the RAG:
-";
\ No newline at end of file
+{code}";
\ No newline at end of file
@@ -0,0 +1,196 @@
+use crate::{
+    manifest::{manifest_dependencies, read_manifest},
+    models::{CodeElement, CodeFile, CrateUsage, CrateUsageReport, ItemKind},
+};
+
+use anyhow::Result;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+// Dependencies declared in the manifest, with the modules and items using each of them. An item
+// uses a crate when its code starts a path with it, or refers to a name imported from it.
+pub fn crate_usage<P: AsRef<Path>, Q: AsRef<Path>>(
+    manifest_path: P,
+    code_files: &[CodeFile<Q>],
+) -> Result<CrateUsageReport> {
+    let manifest = read_manifest(manifest_path)?;
+
+    let mut crates: BTreeMap<String, CrateUsage> = BTreeMap::new();
+    for dependency in manifest_dependencies(&manifest) {
+        let crate_usage = crates
+            .entry(dependency.crate_name())
+            .or_insert_with(|| CrateUsage {
+                name: dependency.crate_name(),
+                package: dependency.package.clone(),
+                kinds: Vec::new(),
+                modules: Vec::new(),
+                elements: Vec::new(),
+            });
+        if !crate_usage.kinds.contains(&dependency.kind) {
+            crate_usage.kinds.push(dependency.kind);
+            crate_usage.kinds.sort();
+        }
+    }
+
+    let crate_names: BTreeSet<String> = crates.keys().cloned().collect();
+    let path_root = Regex::new(r"(?:^|[^:\w])([A-Za-z_]\w*)\s*::").unwrap();
+
+    for code_file in code_files {
+        // The module representing the file is the last element.
+        let Some((module, code_elements)) = code_file.elements.split_last() else {
+            continue;
+        };
+        if module.code_element_id.kind != ItemKind::Mod {
+            continue;
+        }
+
+        let mut module_crates: BTreeSet<String> = module
+            .imports
+            .iter()
+            .filter_map(|import| imported_crate(import))
+            .filter(|name| crate_names.contains(*name))
+            .map(String::from)
+            .collect();
+        module_crates.extend(used_crates(module, &crate_names, &path_root));
+
+        for code_element in code_elements
+            .iter()
+            .filter(|code_element| code_element.code_element_id.kind != ItemKind::DocSection)
+        {
+            for name in used_crates(code_element, &crate_names, &path_root) {
+                if let Some(crate_usage) = crates.get_mut(&name) {
+                    crate_usage
+                        .elements
+                        .push(code_element.code_element_id.clone());
+                }
+                module_crates.insert(name);
+            }
+        }
+
+        for name in module_crates {
+            if let Some(crate_usage) = crates.get_mut(&name) {
+                crate_usage.modules.push(module.code_element_id.clone());
+            }
+        }
+    }
+
+    let mut crates: Vec<CrateUsage> = crates.into_values().collect();
+    for crate_usage in &mut crates {
+        crate_usage.modules.sort();
+        crate_usage.modules.dedup();
+        crate_usage.elements.sort();
+        crate_usage.elements.dedup();
+    }
+
+    let unused_dependencies = crates
+        .iter()
+        .filter(|crate_usage| crate_usage.modules.is_empty())
+        .map(|crate_usage| crate_usage.package.clone())
+        .collect();
+
+    Ok(CrateUsageReport {
+        crates,
+        unused_dependencies,
+    })
+}
+
+pub fn crate_usage_to_markdown(crate_usage_report: &CrateUsageReport) -> String {
+    let mut markdown = String::from(
+        "# Crate usage\n\n| Crate | Package | Kinds | Modules | Items |\n|---|---|---|---|---|\n",
+    );
+
+    for crate_usage in &crate_usage_report.crates {
+        markdown += &format!(
+            "| `{}` | `{}` | {} | {} | {} |\n",
+            crate_usage.name,
+            crate_usage.package,
+            crate_usage
+                .kinds
+                .iter()
+                .map(|kind| kind.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            crate_usage.modules.len(),
+            crate_usage.elements.len()
+        );
+    }
+
+    if !crate_usage_report.unused_dependencies.is_empty() {
+        markdown += "\n## Unused dependencies\n\n";
+        for dependency in &crate_usage_report.unused_dependencies {
+            markdown += &format!("- `{dependency}`\n");
+        }
+    }
+
+    for crate_usage in crate_usage_report
+        .crates
+        .iter()
+        .filter(|crate_usage| !crate_usage.modules.is_empty())
+    {
+        markdown += &format!("\n## `{}`\n\n", crate_usage.name);
+        for code_element_id in crate_usage.modules.iter().chain(&crate_usage.elements) {
+            markdown += &format!(
+                "- `{}` ({}) in `{}`\n",
+                code_element_id.ident, code_element_id.kind, code_element_id.location
+            );
+        }
+    }
+
+    markdown
+}
+
+// Identifiers and keywords written in the code, comments and strings included.
+pub(crate) fn code_idents(code: &str) -> BTreeSet<&str> {
+    code.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|ident| !ident.is_empty())
+        .collect()
+}
+
+// First segment of an import, e.g. `qdrant_client` for `qdrant_client :: qdrant :: Filter`.
+pub(crate) fn imported_crate(import: &str) -> Option<&str> {
+    import
+        .trim_start_matches(" :: ")
+        .split(" :: ")
+        .next()
+        .filter(|segment| !segment.is_empty())
+}
+
+// Name an import is referred to with, its alias if renamed.
+pub(crate) fn imported_name(import: &str) -> &str {
+    match import.split_once(" as ") {
+        Some((_, alias)) => alias,
+        None => import.rsplit(" :: ").next().unwrap_or(import),
+    }
+}
+
+fn used_crates(
+    code_element: &CodeElement,
+    crate_names: &BTreeSet<String>,
+    path_root: &Regex,
+) -> BTreeSet<String> {
+    let code = &code_element.code;
+    let idents = code_idents(code);
+
+    let mut used: BTreeSet<String> = path_root
+        .captures_iter(code)
+        .filter_map(|captures| captures.get(1))
+        .map(|name| name.as_str())
+        .filter(|name| crate_names.contains(*name))
+        .map(String::from)
+        .collect();
+
+    used.extend(
+        code_element
+            .imports
+            .iter()
+            .filter(|import| idents.contains(imported_name(import)))
+            .filter_map(|import| imported_crate(import))
+            .filter(|name| crate_names.contains(*name))
+            .map(String::from),
+    );
+
+    used
+}
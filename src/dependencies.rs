@@ -0,0 +1,218 @@
+use crate::{
+    features::add_feature_gates,
+    linking::{add_linked_implementors, NamespacedItems},
+    manifest::{locked_versions, manifest_dependencies, read_manifest},
+    models::CodeFile,
+    parsing::parse_source_at_location,
+    source::{FsSourceProvider, SourceProvider},
+};
+
+use anyhow::{Error, Result};
+use std::path::{Path, PathBuf};
+
+// Parses the library sources of the dependencies declared in the manifest, from a `cargo vendor`
+// directory or the registry cache, e.g. `~/.cargo/registry/src`. Elements are located under
+// `dependency :: crate_name`, apart from those of the repository. Only the crates in
+// `included_crates` are parsed, by crate or package name, or all the declared ones if empty.
+//
+// The version in Cargo.lock is preferred when several are available, the highest otherwise.
+// Files the parser does not support are skipped, and links between the items of a dependency
+// are not computed, as their number would make it too slow.
+pub fn parse_dependency_sources<P: AsRef<Path>, Q: AsRef<Path>>(
+    manifest_path: P,
+    sources_path: Q,
+    included_crates: &[&str],
+) -> Result<Vec<CodeFile<PathBuf>>> {
+    let manifest = read_manifest(&manifest_path)?;
+    let locked_versions = locked_versions(&manifest_path);
+    let source_directories = source_directories(sources_path.as_ref())?;
+
+    let mut dependencies = manifest_dependencies(&manifest);
+    dependencies.sort_by_key(|dependency| dependency.crate_name());
+    dependencies.dedup_by_key(|dependency| dependency.crate_name());
+
+    for included_crate in included_crates {
+        if !dependencies.iter().any(|dependency| {
+            dependency.crate_name() == *included_crate || dependency.package == *included_crate
+        }) {
+            return Err(Error::msg(format!(
+                "{included_crate} is not a dependency declared in {}",
+                manifest_path.as_ref().display()
+            )));
+        }
+    }
+
+    let mut code_files = Vec::new();
+
+    for dependency in dependencies.iter().filter(|dependency| {
+        included_crates.is_empty()
+            || included_crates.contains(&dependency.crate_name().as_str())
+            || included_crates.contains(&dependency.package.as_str())
+    }) {
+        let locked_versions = locked_versions
+            .get(&dependency.package)
+            .cloned()
+            .unwrap_or_default();
+
+        match crate_directory(&source_directories, &dependency.package, &locked_versions) {
+            Some(crate_directory) => {
+                code_files.extend(parse_crate_sources(
+                    &crate_directory,
+                    &dependency.crate_name(),
+                )?);
+            }
+            None if !included_crates.is_empty() => {
+                return Err(Error::msg(format!(
+                    "Sources of {} not found in {}",
+                    dependency.package,
+                    sources_path.as_ref().display()
+                )));
+            }
+            None => (),
+        }
+    }
+
+    Ok(code_files)
+}
+
+// Adds the dependency items the repository elements refer to through their imports to their
+// dependencies, and the elements to the implementors of the items. Imports of re-exported items
+// are followed to their definitions.
+pub fn link_dependency_sources(
+    code_files: &mut [CodeFile<PathBuf>],
+    dependency_files: &mut [CodeFile<PathBuf>],
+) {
+    let implementors =
+        NamespacedItems::new(dependency_files, "dependency").link_imports(code_files);
+    add_linked_implementors(dependency_files, &implementors);
+}
+
+// Directories that may contain crate sources: those of the given directory, and those one level
+// deeper when it is the registry cache, made of a directory per registry.
+fn source_directories(sources_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut directories = Vec::new();
+
+    for entry in std::fs::read_dir(sources_path)?.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        if path.join("Cargo.toml").is_file() {
+            directories.push(path);
+        } else {
+            directories.extend(
+                std::fs::read_dir(&path)?
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.join("Cargo.toml").is_file()),
+            );
+        }
+    }
+
+    Ok(directories)
+}
+
+// `cargo vendor` names directories after the package, adding the version only when several are
+// vendored, the registry cache always adds it.
+fn crate_directory(
+    source_directories: &[PathBuf],
+    package: &str,
+    locked_versions: &[String],
+) -> Option<PathBuf> {
+    let mut candidates: Vec<(Vec<u64>, bool, &PathBuf)> = source_directories
+        .iter()
+        .filter(|directory| {
+            directory
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name == package
+                        || name
+                            .strip_prefix(package)
+                            .and_then(|rest| rest.strip_prefix('-'))
+                            .is_some_and(|version| {
+                                version.starts_with(|c: char| c.is_ascii_digit())
+                            })
+                })
+        })
+        .filter_map(|directory| {
+            let manifest = read_manifest(directory.join("Cargo.toml")).ok()?;
+            let package_table = manifest.get("package")?;
+            if package_table.get("name")?.as_str()? != package {
+                return None;
+            }
+
+            let version = package_table.get("version")?.as_str()?.to_string();
+            Some((
+                version_key(&version),
+                locked_versions.contains(&version),
+                directory,
+            ))
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+    candidates
+        .last()
+        .map(|(_, _, directory)| directory.to_path_buf())
+}
+
+fn parse_crate_sources(crate_directory: &Path, crate_name: &str) -> Result<Vec<CodeFile<PathBuf>>> {
+    let source_path = crate_directory.join("src");
+    let location_root = format!("dependency :: {crate_name}");
+    let mut code_files = Vec::new();
+
+    for path in FsSourceProvider.list_files(&source_path)? {
+        let Ok(relative_path) = path.strip_prefix(&source_path) else {
+            continue;
+        };
+        // Only the library is of interest.
+        if path.extension().is_none_or(|extension| extension != "rs")
+            || relative_path.starts_with("bin")
+        {
+            continue;
+        }
+
+        // Items of `mod.rs` files are located in the module, as those of `name.rs` files.
+        let location = relative_path
+            .iter()
+            .filter_map(|component| component.to_str())
+            .filter(|component| *component != "mod.rs")
+            .fold(location_root.clone(), |location, component| {
+                format!("{location} :: {}", component.trim_end_matches(".rs"))
+            });
+        let Ok(code) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(mut code_file) = parse_source_at_location(path.clone(), code, location) else {
+            continue;
+        };
+
+        // Paths starting with `crate` refer to the dependency, not to the repository.
+        for code_element in &mut code_file.elements {
+            for import in &mut code_element.imports {
+                if let Some(path) = import.strip_prefix("crate :: ") {
+                    *import = format!("{location_root} :: {path}");
+                }
+            }
+        }
+
+        code_files.push(code_file);
+    }
+
+    add_feature_gates(&mut code_files);
+
+    Ok(code_files)
+}
+
+// Numeric components of a version, pre-release and build metadata aside.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .filter_map(|component| component.parse().ok())
+        .collect()
+}
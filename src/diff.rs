@@ -0,0 +1,374 @@
+use crate::{
+    models::{
+        CodeElement, CodeElementID, CodeFile, DiffReport, ItemKind, ModifiedCodeElement,
+        MovedCodeElement,
+    },
+    parsing::parse_repository_at_revision,
+};
+
+use anyhow::Result;
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{__private::ToTokens, parse_str, Item};
+
+pub fn diff_code_files<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    old_code_files: &[CodeFile<P>],
+    new_code_files: &[CodeFile<Q>],
+) -> DiffReport {
+    let old_elements = index_code_elements(old_code_files);
+    let new_elements = index_code_elements(new_code_files);
+
+    // Elements sharing an ID, e.g. the `fmt` methods of the `Display` and `Debug` impls of a type,
+    // are paired in the order they appear, those left over being removed or added.
+    let mut removed: Vec<&CodeElement> = Vec::new();
+    let mut added: Vec<&CodeElement> = Vec::new();
+    let mut paired: Vec<(&CodeElement, &CodeElement)> = Vec::new();
+
+    for (code_element_id, old_versions) in &old_elements {
+        let new_versions = new_elements
+            .get(code_element_id)
+            .map_or(&[][..], Vec::as_slice);
+
+        paired.extend(
+            old_versions
+                .iter()
+                .copied()
+                .zip(new_versions.iter().copied()),
+        );
+        removed.extend(old_versions.iter().skip(new_versions.len()));
+    }
+    for (code_element_id, new_versions) in &new_elements {
+        let old_count = old_elements.get(code_element_id).map_or(0, Vec::len);
+        added.extend(new_versions.iter().skip(old_count));
+    }
+
+    let moved = extract_moved(&mut removed, &mut added);
+
+    let modified = paired
+        .into_iter()
+        .filter_map(|(old_element, new_element)| compare_code_elements(old_element, new_element))
+        .collect();
+
+    DiffReport {
+        old_commit_id: None,
+        new_commit_id: None,
+        added: added
+            .into_iter()
+            .map(|code_element| code_element.code_element_id.clone())
+            .collect(),
+        removed: removed
+            .into_iter()
+            .map(|code_element| code_element.code_element_id.clone())
+            .collect(),
+        moved,
+        modified,
+    }
+}
+
+pub fn diff_revisions<P: AsRef<std::path::Path>>(
+    repository_path: P,
+    old_revision: &str,
+    new_revision: &str,
+) -> Result<DiffReport> {
+    let old_parsed_revision = parse_repository_at_revision(&repository_path, old_revision, None)?;
+    let new_parsed_revision = parse_repository_at_revision(&repository_path, new_revision, None)?;
+
+    let mut diff_report = diff_code_files(
+        &old_parsed_revision.code_files,
+        &new_parsed_revision.code_files,
+    );
+    diff_report.old_commit_id = Some(old_parsed_revision.commit_id);
+    diff_report.new_commit_id = Some(new_parsed_revision.commit_id);
+
+    Ok(diff_report)
+}
+
+pub fn diff_report_to_markdown(diff_report: &DiffReport) -> String {
+    let mut markdown = String::from("# Changes\n");
+
+    if let (Some(old_commit_id), Some(new_commit_id)) =
+        (&diff_report.old_commit_id, &diff_report.new_commit_id)
+    {
+        markdown += &format!("\nFrom `{old_commit_id}` to `{new_commit_id}`.\n");
+    }
+
+    if !diff_report.added.is_empty() {
+        markdown += "\n## Added\n\n";
+        for code_element_id in &diff_report.added {
+            markdown += &format!("- {}\n", format_code_element_id(code_element_id));
+        }
+    }
+
+    if !diff_report.removed.is_empty() {
+        markdown += "\n## Removed\n\n";
+        for code_element_id in &diff_report.removed {
+            markdown += &format!("- {}\n", format_code_element_id(code_element_id));
+        }
+    }
+
+    if !diff_report.moved.is_empty() {
+        markdown += "\n## Moved\n\n";
+        for moved in &diff_report.moved {
+            markdown += &format!(
+                "- {} to `{}`{}\n",
+                format_code_element_id(&moved.from),
+                moved.to.location,
+                if moved.code_changed {
+                    ", code changed"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    if !diff_report.modified.is_empty() {
+        markdown += "\n## Modified\n\n";
+        for modified in &diff_report.modified {
+            markdown += &format!("- {}\n", format_code_element_id(&modified.code_element_id));
+
+            match (&modified.old_signature, &modified.new_signature) {
+                (Some(old_signature), Some(new_signature)) => {
+                    markdown += &format!(
+                        "  - signature changed from `{old_signature}` to `{new_signature}`\n"
+                    )
+                }
+                (Some(old_signature), None) => {
+                    markdown += &format!("  - signature `{old_signature}` no longer found\n")
+                }
+                (None, Some(new_signature)) => {
+                    markdown += &format!("  - signature is now `{new_signature}`\n")
+                }
+                (None, None) if modified.code_changed => markdown += "  - code changed\n",
+                (None, None) => (),
+            }
+
+            for code_element_id in &modified.added_dependencies {
+                markdown += &format!(
+                    "  - new dependency on {}\n",
+                    format_code_element_id(code_element_id)
+                );
+            }
+            for code_element_id in &modified.removed_dependencies {
+                markdown += &format!(
+                    "  - no longer depends on {}\n",
+                    format_code_element_id(code_element_id)
+                );
+            }
+        }
+    }
+
+    markdown
+}
+
+fn compare_code_elements(
+    old_element: &CodeElement,
+    new_element: &CodeElement,
+) -> Option<ModifiedCodeElement> {
+    // Modules and impl blocks contain their children code, changes are reported on children.
+    let code_changed = ![ItemKind::Impl, ItemKind::Mod].contains(&new_element.code_element_id.kind)
+        && normalize_code(&old_element.code) != normalize_code(&new_element.code);
+
+    let old_signature = signature(old_element);
+    let new_signature = signature(new_element);
+    let (old_signature, new_signature) = if old_signature != new_signature {
+        (old_signature, new_signature)
+    } else {
+        (None, None)
+    };
+
+    let old_dependencies: BTreeSet<&CodeElementID> = old_element.dependencies.iter().collect();
+    let new_dependencies: BTreeSet<&CodeElementID> = new_element.dependencies.iter().collect();
+    let added_dependencies: Vec<CodeElementID> = new_dependencies
+        .difference(&old_dependencies)
+        .map(|code_element_id| (*code_element_id).clone())
+        .collect();
+    let removed_dependencies: Vec<CodeElementID> = old_dependencies
+        .difference(&new_dependencies)
+        .map(|code_element_id| (*code_element_id).clone())
+        .collect();
+
+    if !code_changed
+        && old_signature.is_none()
+        && new_signature.is_none()
+        && added_dependencies.is_empty()
+        && removed_dependencies.is_empty()
+    {
+        return None;
+    }
+
+    Some(ModifiedCodeElement {
+        code_element_id: new_element.code_element_id.clone(),
+        code_changed,
+        old_signature,
+        new_signature,
+        added_dependencies,
+        removed_dependencies,
+    })
+}
+
+// An element is considered moved when a removed element and an added one share ident and kind,
+// and no other candidate makes the pairing ambiguous (unless one of them has the same code).
+fn extract_moved(
+    removed: &mut Vec<&CodeElement>,
+    added: &mut Vec<&CodeElement>,
+) -> Vec<MovedCodeElement> {
+    let mut moved = Vec::new();
+    let mut index = 0;
+
+    while index < removed.len() {
+        let old_element = removed[index];
+        let candidates: Vec<usize> = added
+            .iter()
+            .enumerate()
+            .filter(|(_, new_element)| {
+                new_element.code_element_id.ident == old_element.code_element_id.ident
+                    && new_element.code_element_id.kind == old_element.code_element_id.kind
+            })
+            .map(|(position, _)| position)
+            .collect();
+
+        let same_code = candidates.iter().copied().find(|position| {
+            normalize_code(&added[*position].code) == normalize_code(&old_element.code)
+        });
+
+        let matched = match (same_code, candidates.as_slice()) {
+            (Some(position), _) => Some(position),
+            (None, [position]) => Some(*position),
+            _ => None,
+        };
+
+        if let Some(position) = matched {
+            let new_element = added.remove(position);
+            removed.remove(index);
+
+            moved.push(MovedCodeElement {
+                from: old_element.code_element_id.clone(),
+                to: new_element.code_element_id.clone(),
+                code_changed: same_code.is_none(),
+            });
+        } else {
+            index += 1;
+        }
+    }
+
+    moved
+}
+
+fn format_code_element_id(code_element_id: &CodeElementID) -> String {
+    format!(
+        "`{}` ({}) in `{}`",
+        code_element_id.ident, code_element_id.kind, code_element_id.location
+    )
+}
+
+fn index_code_elements<P: AsRef<std::path::Path>>(
+    code_files: &[CodeFile<P>],
+) -> BTreeMap<&CodeElementID, Vec<&CodeElement>> {
+    let mut code_elements: BTreeMap<&CodeElementID, Vec<&CodeElement>> = BTreeMap::new();
+
+    for code_element in code_files.iter().flat_map(|code_file| &code_file.elements) {
+        code_elements
+            .entry(&code_element.code_element_id)
+            .or_default()
+            .push(code_element);
+    }
+
+    code_elements
+}
+
+// Formatting, plain comments and documentation are not relevant to the comparison.
+fn normalize_code(code: &str) -> String {
+    code.parse::<TokenStream>()
+        .map(|token_stream| strip_documentation(token_stream).to_string())
+        .unwrap_or_else(|_| code.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+// Removes the `#[doc = ...]` attributes doc comments are turned into, inner ones included, at any
+// depth. Other `doc` attributes, e.g. `#[doc(hidden)]`, are kept.
+fn strip_documentation(token_stream: TokenStream) -> TokenStream {
+    let tokens: Vec<TokenTree> = token_stream.into_iter().collect();
+    let mut stripped = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+
+    while index < tokens.len() {
+        if let TokenTree::Punct(punct) = &tokens[index] {
+            if punct.as_char() == '#' {
+                let attribute_index = match tokens.get(index + 1) {
+                    Some(TokenTree::Punct(punct)) if punct.as_char() == '!' => index + 2,
+                    _ => index + 1,
+                };
+                if let Some(TokenTree::Group(group)) = tokens.get(attribute_index) {
+                    if group.delimiter() == Delimiter::Bracket && is_documentation(group) {
+                        index = attribute_index + 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        stripped.push(match &tokens[index] {
+            TokenTree::Group(group) => {
+                let mut stripped_group =
+                    Group::new(group.delimiter(), strip_documentation(group.stream()));
+                stripped_group.set_span(group.span());
+                TokenTree::Group(stripped_group)
+            }
+            token => token.clone(),
+        });
+        index += 1;
+    }
+
+    stripped.into_iter().collect()
+}
+
+fn is_documentation(attribute: &Group) -> bool {
+    let mut tokens = attribute.stream().into_iter();
+    matches!(
+        (tokens.next(), tokens.next()),
+        (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(punct)))
+            if ident == "doc" && punct.as_char() == '='
+    )
+}
+
+pub(crate) fn signature(code_element: &CodeElement) -> Option<String> {
+    let code = &code_element.code;
+
+    match code_element.code_element_id.kind {
+        ItemKind::Fn => match parse_str::<Item>(code) {
+            Ok(Item::Fn(item_fn)) => Some(item_fn.sig.to_token_stream().to_string()),
+            _ => parse_str::<syn::TraitItemFn>(code)
+                .ok()
+                .map(|trait_item_fn| trait_item_fn.sig.to_token_stream().to_string()),
+        },
+        ItemKind::Const => match parse_str::<Item>(code) {
+            Ok(Item::Const(item_const)) => Some(format!(
+                "const {} : {}",
+                item_const.ident,
+                item_const.ty.to_token_stream()
+            )),
+            _ => None,
+        },
+        ItemKind::Static => match parse_str::<Item>(code) {
+            Ok(Item::Static(item_static)) => Some(format!(
+                "static {}{} : {}",
+                if matches!(item_static.mutability, syn::StaticMutability::Mut(_)) {
+                    "mut "
+                } else {
+                    ""
+                },
+                item_static.ident,
+                item_static.ty.to_token_stream()
+            )),
+            _ => None,
+        },
+        ItemKind::Type => match parse_str::<Item>(code) {
+            Ok(Item::Type(mut item_type)) => {
+                item_type.attrs.clear();
+                Some(item_type.to_token_stream().to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -1,139 +1,710 @@
 use crate::{
-    models::{CodeElement, CodeFile, DocumentedCodeElement, FieldDescription, ItemKind},
-    openai::generate_documentation,
-    parsing::parse_file,
+    doctests::{mark_untested, ExampleTester},
+    failures::apply_failure_analysis,
+    git::toplevel,
+    intra_doc_links::IntraDocLinker,
+    models::{
+        CodeElement, CodeElementID, CodeFile, DocGenOptions, DocumentationEdit,
+        DocumentationSection, DocumentedCodeElement, ExampleFailure, ExistingDocumentation,
+        FieldDescription, ItemKind, StyleGuide, StyleViolation,
+    },
+    openai::{fix_documentation_example, fix_documentation_style, generate_documentation},
+    parsing::parse_source,
+    patch::{line_edits, unified_diff},
+    rate_limit::RateLimiter,
+    source::FsSourceProvider,
+    style::{forbidden_word_regexes, heading, required_sections, section_text, style_violations},
+    templates::Templates,
+    unsafety::is_unsafe_declaration,
 };
 
 use anyhow::Result;
+use futures::StreamExt;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{
+    spanned::Spanned,
+    visit::{self, Visit},
+};
+
+// What the requests of the elements share.
+struct Generation<'a> {
+    chat_model: &'a str,
+    openai_api_key: &'a str,
+    rate_limiter: RateLimiter,
+    example_tester: Option<ExampleTester>,
+    templates: Templates,
+    style_guide: &'a StyleGuide,
+    forbidden_words: Vec<(String, Regex)>,
+}
 
+// Elements of all the files are documented concurrently, up to the options limits, those using or
+// containing others after them. Results keep the order of the elements whatever the order
+// requests complete in.
+//
+// The edits of the files are computed when writing inside the repository or with the `dry_run` or
+// `patch_path` options, and returned. Files are only written inside the repository without
+// `dry_run`.
 pub async fn document_repository<P: AsRef<std::path::Path>, W: AsRef<std::path::Path> + Clone>(
     chat_model: &str,
     code_files: Vec<CodeFile<P>>,
     kinds_to_document: &[ItemKind],
     openai_api_key: &str,
+    options: &DocGenOptions,
     write_inside_repository: bool,
     write_to_json_path: Option<W>,
-) -> Result<()> {
-    for code_file in code_files {
-        crate::doc_gen::document_file(
-            chat_model,
-            &code_file.elements,
-            code_file.path,
-            kinds_to_document,
-            openai_api_key,
-            write_inside_repository,
-            write_to_json_path.clone(),
-        )
-        .await?;
+) -> Result<Vec<DocumentationEdit>> {
+    let code_elements: Vec<(usize, &CodeElement)> = code_files
+        .iter()
+        .enumerate()
+        .flat_map(|(file_index, code_file)| {
+            code_file
+                .elements
+                .iter()
+                .map(move |code_element| (file_index, code_element))
+        })
+        .collect();
+
+    let raw_documented_code_elements = generate_documentations(
+        chat_model,
+        &code_elements
+            .iter()
+            .map(|(_, code_element)| *code_element)
+            .collect::<Vec<&CodeElement>>(),
+        kinds_to_document,
+        openai_api_key,
+        options,
+    )
+    .await?;
+    // Each documentation is kept with the occurrence of its element among those of the file
+    // sharing its ID.
+    let mut file_raw_documented_code_elements = vec![Vec::new(); code_files.len()];
+    let mut occurrences: BTreeMap<(usize, &CodeElementID), usize> = BTreeMap::new();
+    for ((file_index, code_element), raw_documented_code_element) in
+        code_elements.iter().zip(&raw_documented_code_elements)
+    {
+        let occurrence = occurrences
+            .entry((*file_index, &code_element.code_element_id))
+            .or_default();
+        if let Some(raw_documented_code_element) = raw_documented_code_element {
+            file_raw_documented_code_elements[*file_index]
+                .push((*occurrence, raw_documented_code_element));
+        }
+        *occurrence += 1;
     }
 
-    Ok(())
+    let mut documentation_edits = Vec::new();
+    if write_inside_repository || options.dry_run || options.patch_path.is_some() {
+        let mut patch = String::new();
+
+        for (code_file, raw_documented_code_elements) in
+            code_files.iter().zip(&file_raw_documented_code_elements)
+        {
+            let path = code_file.path.as_ref();
+            let Some((source, documented_source)) = documented_source(
+                path,
+                raw_documented_code_elements,
+                options.existing_documentation,
+                &options.style_guide,
+            )?
+            else {
+                continue;
+            };
+            if documented_source == source {
+                continue;
+            }
+
+            documentation_edits.extend(line_edits(path, &source, &documented_source));
+            if options.patch_path.is_some() {
+                patch += &unified_diff(&patch_label(path), &source, &documented_source);
+            }
+            if write_inside_repository && !options.dry_run {
+                std::fs::write(path, documented_source)?;
+            }
+        }
+
+        if let Some(patch_path) = &options.patch_path {
+            std::fs::write(patch_path, patch)?;
+        }
+    }
+    if let Some(path) = write_to_json_path {
+        write_documentation_to_file(
+            path,
+            &raw_documented_code_elements
+                .into_iter()
+                .flatten()
+                .collect::<Vec<DocumentedCodeElement>>(),
+        )?;
+    }
+
+    Ok(documentation_edits)
 }
 
+// Documents the elements of a single file, as `document_repository` does.
 pub async fn document_file<P: AsRef<std::path::Path>, W: AsRef<std::path::Path> + Clone>(
     chat_model: &str,
-    code_elements: &[CodeElement],
-    file_to_document_path: P,
+    code_file: CodeFile<P>,
     kinds_to_document: &[ItemKind],
     openai_api_key: &str,
+    options: &DocGenOptions,
     write_inside_repository: bool,
     write_to_json_path: Option<W>,
-) -> Result<()> {
-    let mut raw_documented_code_elements = Vec::with_capacity(code_elements.len());
-
-    for code_element in code_elements {
-        let ident = code_element.code_element_id.ident.clone();
-        let kind = code_element.code_element_id.kind.clone();
-        let location = &code_element.code_element_id.location;
-        let code = code_element.code.clone();
-
-        //TODO: parallelize
-        if kinds_to_document.contains(&kind) || kinds_to_document.contains(&ItemKind::All) {
-            if let Ok(raw_documented_code_element) =
-                generate_documentation(chat_model, openai_api_key, &ident, &kind, location, &code)
-                    .await
+) -> Result<Vec<DocumentationEdit>> {
+    document_repository(
+        chat_model,
+        vec![code_file],
+        kinds_to_document,
+        openai_api_key,
+        options,
+        write_inside_repository,
+        write_to_json_path,
+    )
+    .await
+}
+
+// One result per element, None for those not documented or whose generation failed. Elements are
+// documented leaves first, the documentation generated for the items they contain or use being
+// part of their prompt. Names between backticks are linked once all of them are documented.
+async fn generate_documentations(
+    chat_model: &str,
+    code_elements: &[&CodeElement],
+    kinds_to_document: &[ItemKind],
+    openai_api_key: &str,
+    options: &DocGenOptions,
+) -> Result<Vec<Option<DocumentedCodeElement>>> {
+    let generation = Generation {
+        chat_model,
+        openai_api_key,
+        rate_limiter: RateLimiter::new(options.requests_per_minute, options.tokens_per_minute)?,
+        example_tester: match &options.example_validation {
+            Some(example_validation) => Some(ExampleTester::new(example_validation).await?),
+            None => None,
+        },
+        templates: Templates::new(&options.prompt_templates)?,
+        style_guide: &options.style_guide,
+        forbidden_words: forbidden_word_regexes(&options.style_guide),
+    };
+
+    let to_document: Vec<bool> = code_elements
+        .iter()
+        .map(|code_element| {
+            let kind = &code_element.code_element_id.kind;
+            // Documentation sections are already documentation.
+            kind != &ItemKind::DocSection
+                && (kinds_to_document.contains(kind) || kinds_to_document.contains(&ItemKind::All))
+                && !(options.existing_documentation == ExistingDocumentation::Skip
+                    && is_documented(code_element))
+        })
+        .collect();
+    let prerequisites = prerequisites(code_elements, &to_document);
+
+    let mut raw_documented_code_elements: Vec<Option<DocumentedCodeElement>> =
+        vec![None; code_elements.len()];
+
+    for level in documentation_levels(&prerequisites, &to_document) {
+        let generations: Vec<_> = level
+            .iter()
+            .map(|&index| {
+                let related_documentation: Vec<&DocumentedCodeElement> = prerequisites[index]
+                    .iter()
+                    .filter_map(|&prerequisite| raw_documented_code_elements[prerequisite].as_ref())
+                    .collect();
+
+                generate_element_documentation(
+                    index,
+                    &generation,
+                    code_elements[index],
+                    related_documentation,
+                )
+            })
+            .collect();
+
+        let level_raw_documented_code_elements: Vec<(usize, Option<DocumentedCodeElement>)> =
+            futures::stream::iter(generations)
+                .buffer_unordered(options.max_concurrent_requests.unwrap_or(1).max(1))
+                .collect()
+                .await;
+
+        for (index, raw_documented_code_element) in level_raw_documented_code_elements {
+            raw_documented_code_elements[index] = raw_documented_code_element;
+        }
+    }
+
+    if options.intra_doc_links {
+        let intra_doc_linker = IntraDocLinker::new(code_elements);
+        for (code_element, raw_documented_code_element) in
+            code_elements.iter().zip(&mut raw_documented_code_elements)
+        {
+            if let Some(raw_documented_code_element) = raw_documented_code_element {
+                raw_documented_code_element.unresolved_references = intra_doc_linker
+                    .link(raw_documented_code_element, &code_element.code_element_id);
+            }
+        }
+    }
+
+    Ok(raw_documented_code_elements)
+}
+
+// Elements to document among the children and dependencies of each element to document.
+fn prerequisites(code_elements: &[&CodeElement], to_document: &[bool]) -> Vec<Vec<usize>> {
+    let indexes: BTreeMap<&CodeElementID, usize> = code_elements
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| to_document[*index])
+        .map(|(index, code_element)| (&code_element.code_element_id, index))
+        .collect();
+
+    code_elements
+        .iter()
+        .enumerate()
+        .map(|(index, code_element)| {
+            if !to_document[index] {
+                return Vec::new();
+            }
+
+            let mut prerequisites: Vec<usize> = code_element
+                .children
+                .iter()
+                .chain(&code_element.dependencies)
+                .filter_map(|code_element_id| indexes.get(code_element_id).copied())
+                .filter(|prerequisite| *prerequisite != index)
+                .collect();
+            prerequisites.sort();
+            prerequisites.dedup();
+            prerequisites
+        })
+        .collect()
+}
+
+// Groups of elements that can be documented concurrently, each after those it depends on. Cycles,
+// e.g. mutually recursive functions, are broken at the first element in the original order.
+fn documentation_levels(prerequisites: &[Vec<usize>], to_document: &[bool]) -> Vec<Vec<usize>> {
+    let mut remaining: BTreeSet<usize> = (0..prerequisites.len())
+        .filter(|index| to_document[*index])
+        .collect();
+    let mut levels = Vec::new();
+
+    while let Some(&first) = remaining.first() {
+        let mut level: Vec<usize> = remaining
+            .iter()
+            .copied()
+            .filter(|index| {
+                prerequisites[*index]
+                    .iter()
+                    .all(|prerequisite| !remaining.contains(prerequisite))
+            })
+            .collect();
+        if level.is_empty() {
+            level.push(first);
+        }
+
+        for index in &level {
+            remaining.remove(index);
+        }
+        levels.push(level);
+    }
+
+    levels
+}
+
+// From the code of the element, the inner documentation of modules being looked up as it is
+// when inserting documentation.
+// The code of file modules is the whole file, whose first item may have outer documentation of
+// its own, so only their inner documentation counts.
+fn is_documented(code_element: &CodeElement) -> bool {
+    let code_element_id = &code_element.code_element_id;
+    let code_lines: Vec<&str> = code_element.code.lines().collect();
+    let file_module = code_element_id.kind == ItemKind::Mod
+        && !is_inline_module(&code_element_id.ident, &code_element_id.location);
+
+    (!file_module && !documentation_lines(&code_lines, 1, false).is_empty())
+        || (code_element_id.kind == ItemKind::Mod
+            && !documentation_lines(
+                &code_lines,
+                inner_documentation_start(&code_lines, 1, !file_module),
+                true,
+            )
+            .is_empty())
+}
+
+// Modules of files are located at their own path.
+fn is_inline_module(ident: &str, location: &str) -> bool {
+    location.rsplit(" :: ").next() != Some(ident)
+}
+
+// Line, 1-based, the inner documentation of the module whose item starts at the line starts at:
+// the line itself for modules of files, the one after the opening brace for inline modules.
+fn inner_documentation_start<S: AsRef<str>>(
+    code_lines: &[S],
+    line_start: usize,
+    inline_module: bool,
+) -> usize {
+    if !inline_module {
+        return line_start;
+    }
+
+    // Declarations without a body, e.g. `mod parsing;`, end before any brace.
+    (line_start.max(1)..=code_lines.len())
+        .map(|line| (line, code_lines[line - 1].as_ref().trim_start()))
+        .filter(|(_, code_line)| {
+            !["#", "//", "/*", "*"]
+                .iter()
+                .any(|prefix| code_line.starts_with(prefix))
+        })
+        .find(|(_, code_line)| code_line.contains('{') || code_line.contains(';'))
+        .filter(|(_, code_line)| code_line.contains('{'))
+        .map_or(line_start, |(line, _)| line + 1)
+}
+
+// The index is returned along with the result, requests completing in any order.
+async fn generate_element_documentation(
+    index: usize,
+    generation: &Generation<'_>,
+    code_element: &CodeElement,
+    related_documentation: Vec<&DocumentedCodeElement>,
+) -> (usize, Option<DocumentedCodeElement>) {
+    let Ok(mut raw_documented_code_element) = generate_documentation(
+        generation.chat_model,
+        generation.openai_api_key,
+        code_element,
+        &related_documentation,
+        &generation.templates,
+        generation.style_guide,
+        Some(&generation.rate_limiter),
+    )
+    .await
+    else {
+        return (index, None);
+    };
+
+    if generation.style_guide.on_violation == StyleViolation::Fix {
+        let violations = style_violations(
+            generation.style_guide,
+            &generation.forbidden_words,
+            code_element,
+            &raw_documented_code_element,
+        );
+        if !violations.is_empty() {
+            if let Ok(fixed_documented_code_element) = fix_documentation_style(
+                generation.chat_model,
+                generation.openai_api_key,
+                code_element,
+                &raw_documented_code_element,
+                &violations,
+                &generation.templates,
+                generation.style_guide,
+                Some(&generation.rate_limiter),
+            )
+            .await
             {
-                raw_documented_code_elements.push(raw_documented_code_element);
+                raw_documented_code_element = fixed_documented_code_element;
             }
         }
     }
 
-    if write_inside_repository {
-        put_documentation_inside_repository(file_to_document_path, &raw_documented_code_elements)?;
+    if let Some(failure_analysis) = &code_element.failure_analysis {
+        apply_failure_analysis(&mut raw_documented_code_element, failure_analysis);
     }
-    if let Some(path) = write_to_json_path {
-        write_documentation_to_file(path, &raw_documented_code_elements)?;
+    // Only unsafe functions and traits have safety requirements.
+    if !is_unsafe_declaration(code_element) {
+        raw_documented_code_element.safety_section.clear();
     }
 
-    Ok(())
+    // Examples are only part of the documentation of functions, and of the kinds the style guide
+    // requires them for.
+    if let Some(example_tester) = &generation.example_tester {
+        let kind = &code_element.code_element_id.kind;
+        if (kind == &ItemKind::Fn
+            || generation
+                .style_guide
+                .required_sections
+                .get(kind)
+                .is_some_and(|sections| sections.contains(&DocumentationSection::Examples)))
+            && !raw_documented_code_element
+                .example_section
+                .trim()
+                .is_empty()
+        {
+            validate_example(
+                generation,
+                code_element,
+                &mut raw_documented_code_element,
+                example_tester,
+            )
+            .await;
+        }
+    }
+
+    // Violations left after fixing the documentation, or introduced by the example validation.
+    raw_documented_code_element.style_violations = style_violations(
+        generation.style_guide,
+        &generation.forbidden_words,
+        code_element,
+        &raw_documented_code_element,
+    );
+    raw_documented_code_element.unmatched_field_descriptions =
+        unmatched_field_descriptions(code_element, &raw_documented_code_element);
+
+    (index, Some(raw_documented_code_element))
+}
+
+// Tests the example, asking the model to fix it with the output of the failing doctest. Examples
+// that cannot be tested, e.g. when rustdoc is missing, are left as they are.
+async fn validate_example(
+    generation: &Generation<'_>,
+    code_element: &CodeElement,
+    raw_documented_code_element: &mut DocumentedCodeElement,
+    example_tester: &ExampleTester,
+) {
+    let example_validation = &example_tester.example_validation;
+
+    for attempt in 0..=example_validation.max_retries {
+        let example = &raw_documented_code_element.example_section;
+        let doctest_output = match example_tester.test(example).await {
+            Ok(None) | Err(_) => return,
+            Ok(Some(doctest_output)) => doctest_output,
+        };
+        if attempt == example_validation.max_retries {
+            break;
+        }
+
+        match fix_documentation_example(
+            generation.chat_model,
+            generation.openai_api_key,
+            code_element,
+            example,
+            &doctest_output,
+            Some(&generation.rate_limiter),
+        )
+        .await
+        {
+            Ok(fixed_example) => raw_documented_code_element.example_section = fixed_example,
+            Err(_) => break,
+        }
+    }
+
+    raw_documented_code_element.example_section = match example_validation.on_failure {
+        ExampleFailure::Drop => String::new(),
+        ExampleFailure::Mark => mark_untested(&raw_documented_code_element.example_section),
+    };
 }
 
 fn documentation_formatter(
     raw_element: &DocumentedCodeElement,
+    style_guide: &StyleGuide,
 ) -> (String, Option<Vec<FieldDescription>>) {
-    let documentation = match raw_element.kind.as_str() {
-        "fn" => {
-            let doc_error_section = if raw_element.error_possible {
-                format!("\n\n# Errors \n\n{}", raw_element.error_section)
-            } else {
-                String::new()
-            };
+    let is_fn = raw_element.kind == "fn";
+    let required_sections = required_sections(style_guide, &raw_element.kind);
 
-            let doc_panic_section = if raw_element.panic_possible {
-                format!("\n\n# Panics \n\n{}", raw_element.panic_section)
-            } else {
-                String::new()
-            };
+    // Sections of functions, the safety one of traits, and the sections the style guide requires.
+    // Examples failing as doctests may have been dropped.
+    let mut documentation = raw_element.general_description.clone();
+    for (section, included) in [
+        (
+            DocumentationSection::Errors,
+            is_fn && raw_element.error_possible,
+        ),
+        (
+            DocumentationSection::Panics,
+            is_fn && raw_element.panic_possible,
+        ),
+        (
+            DocumentationSection::Safety,
+            is_fn || raw_element.kind == "trait",
+        ),
+        (DocumentationSection::Examples, is_fn),
+    ] {
+        let text = section_text(raw_element, section);
+        if (included || required_sections.contains(&section)) && !text.trim().is_empty() {
+            documentation += &format!("\n\n# {} \n\n{text}", heading(style_guide, section));
+        }
+    }
 
-            let formatted_documentation = format!(
-                "{}{}{} \n\n# Examples \n\n{}",
-                raw_element.general_description,
-                doc_error_section,
-                doc_panic_section,
-                raw_element.example_section
-            );
+    let prefix = if raw_element.kind == "mod" {
+        "//!"
+    } else {
+        "///"
+    };
 
-            formatted_documentation
-                .lines()
-                .map(|line| format!("/// {line}"))
-                .collect::<Vec<String>>()
-                .join("\n")
-        }
-        "mod" => raw_element
-            .general_description
-            .lines()
-            .map(|line| format!("//! {line}"))
-            .collect::<Vec<String>>()
-            .join("\n"),
-        _ => raw_element
-            .general_description
+    (
+        documentation
             .lines()
-            .map(|line| format!("/// {line}"))
+            .map(|line| format!("{prefix} {line}"))
             .collect::<Vec<String>>()
             .join("\n"),
-    };
-
-    (
-        documentation,
         raw_element.fields_or_variants_descriptions.clone(),
     )
 }
 
+// Start of the `occurrence`-th element with the ID of the documentation, several elements sharing
+// an ID, e.g. the `fmt` methods of the `Display` and `Debug` impls of a type.
 fn find_start(
     code_elements: &[CodeElement],
     raw_documented_code_element: &DocumentedCodeElement,
+    occurrence: usize,
 ) -> Option<usize> {
-    if let Some(code_element) = code_elements.iter().find(|code_element| {
-        code_element.code_element_id.ident == raw_documented_code_element.ident
-            && code_element.code_element_id.kind.to_string() == raw_documented_code_element.kind
-            && code_element.code_element_id.location == raw_documented_code_element.location
-    }) {
+    if let Some(code_element) = code_elements
+        .iter()
+        .filter(|code_element| {
+            code_element.code_element_id.ident == raw_documented_code_element.ident
+                && code_element.code_element_id.kind.to_string() == raw_documented_code_element.kind
+                && code_element.code_element_id.location == raw_documented_code_element.location
+        })
+        .nth(occurrence)
+    {
         return Some(code_element.line_start);
     }
 
     None
 }
 
+// Documentation of the fields and variants of the struct, enum or union starting at the line,
+// with the line to insert it at, above their attributes. Descriptions are
+// matched by ident, or by index for the fields of tuple structs. Fields sharing their line with
+// other code, e.g. those of `struct Pair(u8, u8);`, are left undocumented.
+fn fields_documentation(
+    file: &syn::File,
+    code_lines: &[&str],
+    ident: &str,
+    line_start: usize,
+    fields_or_variants_descriptions: &[FieldDescription],
+) -> Vec<(usize, String)> {
+    if fields_or_variants_descriptions.is_empty() {
+        return Vec::new();
+    }
+
+    let mut visitor = FieldsVisitor {
+        ident,
+        line_start,
+        fields: Vec::new(),
+    };
+    visitor.visit_file(file);
+
+    visitor
+        .fields
+        .into_iter()
+        .filter_map(|(field_ident, line, column)| {
+            let (_, description) =
+                fields_or_variants_descriptions
+                    .iter()
+                    .find(|(described_ident, _)| {
+                        described_ident.trim().trim_matches('`') == field_ident
+                    })?;
+            if indentation(code_lines.get(line - 1)?).chars().count() != column {
+                return None;
+            }
+
+            let documentation = description
+                .trim()
+                .lines()
+                .map(|description_line| format!("/// {description_line}"))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            Some((line, documentation))
+        })
+        .collect()
+}
+
+// Descriptions are expected for the fields and variants of structs, enums and unions only.
+fn unmatched_field_descriptions(
+    code_element: &CodeElement,
+    raw_documented_code_element: &DocumentedCodeElement,
+) -> Vec<String> {
+    let field_idents: Vec<String> = match syn::parse_str::<syn::Item>(&code_element.code) {
+        Ok(syn::Item::Enum(item_enum)) => item_enum
+            .variants
+            .iter()
+            .map(|variant| variant.ident.to_string())
+            .collect(),
+        Ok(syn::Item::Struct(item_struct)) => item_struct
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| field_ident(index, field))
+            .collect(),
+        Ok(syn::Item::Union(item_union)) => item_union
+            .fields
+            .named
+            .iter()
+            .enumerate()
+            .map(|(index, field)| field_ident(index, field))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    raw_documented_code_element
+        .fields_or_variants_descriptions
+        .iter()
+        .flatten()
+        .map(|(described_ident, _)| described_ident.trim().trim_matches('`'))
+        .filter(|described_ident| !field_idents.iter().any(|ident| ident == described_ident))
+        .map(String::from)
+        .collect()
+}
+
+// Fields of tuple structs are named by their index, as told in the system message.
+fn field_ident(index: usize, field: &syn::Field) -> String {
+    field
+        .ident
+        .as_ref()
+        .map_or_else(|| index.to_string(), |ident| ident.to_string())
+}
+
+// Fields and variants, with the line and column they start at, of the struct, enum or union whose
+// ident and starting line are given.
+struct FieldsVisitor<'a> {
+    ident: &'a str,
+    line_start: usize,
+    fields: Vec<(String, usize, usize)>,
+}
+
+impl FieldsVisitor<'_> {
+    fn is_documented_item(&self, ident: &syn::Ident, span: proc_macro2::Span) -> bool {
+        ident == self.ident && span.start().line == self.line_start
+    }
+
+    fn add_fields(&mut self, fields: &syn::Fields) {
+        self.fields
+            .extend(fields.iter().enumerate().map(|(index, field)| {
+                (
+                    field_ident(index, field),
+                    field.span().start().line,
+                    field.span().start().column,
+                )
+            }));
+    }
+}
+
+impl<'ast> Visit<'ast> for FieldsVisitor<'_> {
+    fn visit_item_enum(&mut self, node: &'ast syn::ItemEnum) {
+        if self.is_documented_item(&node.ident, node.span()) {
+            self.fields.extend(node.variants.iter().map(|variant| {
+                (
+                    variant.ident.to_string(),
+                    variant.span().start().line,
+                    variant.span().start().column,
+                )
+            }));
+        }
+        visit::visit_item_enum(self, node);
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast syn::ItemStruct) {
+        if self.is_documented_item(&node.ident, node.span()) {
+            self.add_fields(&node.fields);
+        }
+        visit::visit_item_struct(self, node);
+    }
+
+    fn visit_item_union(&mut self, node: &'ast syn::ItemUnion) {
+        if self.is_documented_item(&node.ident, node.span()) {
+            self.add_fields(&syn::Fields::Named(node.fields.clone()));
+        }
+        visit::visit_item_union(self, node);
+    }
+}
+
 pub(crate) fn pattern_formatter(ident: &str, kind: &ItemKind) -> String {
     match kind {
         ItemKind::Fn => format!("fn {ident}"),
@@ -146,54 +717,360 @@ pub(crate) fn pattern_formatter(ident: &str, kind: &ItemKind) -> String {
     }
 }
 
+// Elements and fields already documented are handled according to `existing_documentation`, from
+// the documentation lines they start with.
 pub fn put_documentation_inside_repository<P: AsRef<std::path::Path>>(
     file_to_document_path: P,
     raw_documented_code_elements: &[DocumentedCodeElement],
+    existing_documentation: ExistingDocumentation,
+    style_guide: &StyleGuide,
 ) -> Result<()> {
     let path = file_to_document_path.as_ref();
 
-    if let Some(extension) = path.extension() {
-        if extension == "rs" {
-            // TODO: provide support for non-UTF-8 paths
-            let mut location = path.to_str().unwrap().to_owned();
-            if let Some(pos) = location.find("/src/") {
-                location = location[pos + 5..].to_owned();
-            }
-            location = location
-                .trim_end_matches(".rs")
-                .replace('/', " :: ")
-                .to_string();
+    // Elements sharing an ID are expected to be documented in the order they appear.
+    let mut occurrences: BTreeMap<(&str, &str, &str), usize> = BTreeMap::new();
+    let raw_documented_code_elements: Vec<(usize, &DocumentedCodeElement)> =
+        raw_documented_code_elements
+            .iter()
+            .map(|raw_documented_code_element| {
+                let occurrence = occurrences
+                    .entry((
+                        raw_documented_code_element.ident.as_str(),
+                        raw_documented_code_element.kind.as_str(),
+                        raw_documented_code_element.location.as_str(),
+                    ))
+                    .or_default();
+                *occurrence += 1;
+                (*occurrence - 1, raw_documented_code_element)
+            })
+            .collect();
+
+    if let Some((source, documented_source)) = documented_source(
+        path,
+        &raw_documented_code_elements,
+        existing_documentation,
+        style_guide,
+    )? {
+        if documented_source != source {
+            std::fs::write(path, documented_source)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Source of the file before and after inserting the documentation, None for files other than
+// Rust ones. Edits are computed against a single parse of the file, then applied from the bottom
+// so that the lines of those above are left unchanged. Line endings and the final newline of the
+// file are preserved.
+fn documented_source(
+    path: &std::path::Path,
+    raw_documented_code_elements: &[(usize, &DocumentedCodeElement)],
+    existing_documentation: ExistingDocumentation,
+    style_guide: &StyleGuide,
+) -> Result<Option<(String, String)>> {
+    if path.extension().is_none_or(|extension| extension != "rs") {
+        return Ok(None);
+    }
+
+    let source = std::fs::read_to_string(path)?;
+    let code_elements = parse_source(&FsSourceProvider, path, source.clone())?.elements;
+    let file = syn::parse_file(&source)?;
+    let code_lines: Vec<&str> = source.lines().collect();
+
+    let mut source_edits: Vec<SourceEdit> = raw_documented_code_elements
+        .iter()
+        .filter_map(|(occurrence, raw_documented_code_element)| {
+            let line_start = find_start(&code_elements, raw_documented_code_element, *occurrence)?;
+            Some(element_edits(
+                &file,
+                &code_lines,
+                raw_documented_code_element,
+                line_start,
+                existing_documentation,
+                style_guide,
+            ))
+        })
+        .flatten()
+        .collect();
+    source_edits.sort_by_key(|source_edit| {
+        std::cmp::Reverse((source_edit.line_start, source_edit.line_end))
+    });
 
-            let file_raw_documented_code_elements: Vec<&DocumentedCodeElement> =
-                raw_documented_code_elements
+    let mut documented_lines: Vec<String> =
+        code_lines.iter().map(|line| line.to_string()).collect();
+    let mut edited_from = documented_lines.len() + 1;
+    for source_edit in source_edits {
+        // Elements documented twice, insertions at the line of the last edit included.
+        if source_edit.line_end > edited_from
+            || (source_edit.line_start == source_edit.line_end
+                && source_edit.line_end == edited_from)
+        {
+            continue;
+        }
+        documented_lines.splice(
+            source_edit.line_start - 1..source_edit.line_end - 1,
+            source_edit.lines,
+        );
+        edited_from = source_edit.line_start;
+    }
+
+    let line_ending = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut documented_source = documented_lines.join(line_ending);
+    if source.ends_with('\n') {
+        documented_source += line_ending;
+    }
+
+    Ok(Some((source, documented_source)))
+}
+
+// Replaces the lines `line_start..line_end` of the source, 1-based, with the given ones.
+struct SourceEdit {
+    line_start: usize,
+    line_end: usize,
+    lines: Vec<String>,
+}
+
+// Edits documenting the element starting at the line, and its fields or variants, indented as
+// they are. Existing documentation is replaced along with the attributes and comments it is mixed
+// with, which are kept after the new documentation.
+fn element_edits(
+    file: &syn::File,
+    code_lines: &[&str],
+    raw_documented_code_element: &DocumentedCodeElement,
+    line_start: usize,
+    existing_documentation: ExistingDocumentation,
+    style_guide: &StyleGuide,
+) -> Vec<SourceEdit> {
+    let (documentation, fields_or_variants_descriptions) =
+        documentation_formatter(raw_documented_code_element, style_guide);
+    let inner = raw_documented_code_element.kind == "mod";
+    // Elements of inline modules start at the line after the first one of the item.
+    let documentation_start = if inner {
+        let inline_module = is_inline_module(
+            &raw_documented_code_element.ident,
+            &raw_documented_code_element.location,
+        );
+        inner_documentation_start(
+            code_lines,
+            if inline_module {
+                line_start - 1
+            } else {
+                line_start
+            },
+            inline_module,
+        )
+    } else {
+        line_start
+    };
+
+    let documentation_edit = |line: usize, documentation: &str, inner: bool| {
+        let existing_lines = documentation_lines(code_lines, line, inner);
+        // The indentation of the first line of code, for modules starting with a blank line.
+        let indentation = code_lines
+            .iter()
+            .skip(line - 1)
+            .find(|code_line| !code_line.trim().is_empty())
+            .map_or("", |code_line| indentation(code_line));
+
+        match (existing_documentation, existing_lines.last()) {
+            (_, None) => Some(SourceEdit {
+                line_start: line,
+                line_end: line,
+                lines: indent(documentation, indentation),
+            }),
+            (ExistingDocumentation::Skip, Some(_)) => None,
+            (ExistingDocumentation::Replace, Some(&last_line)) => {
+                let first_line = existing_lines[0];
+                let mut lines = indent(documentation, indentation);
+                lines.extend(
+                    (first_line..=last_line)
+                        .filter(|code_line| !existing_lines.contains(code_line))
+                        .map(|code_line| code_lines[code_line - 1].to_string()),
+                );
+                Some(SourceEdit {
+                    line_start: first_line,
+                    line_end: last_line + 1,
+                    lines,
+                })
+            }
+            (ExistingDocumentation::Merge, Some(&last_line)) => {
+                let existing_text: Vec<&str> = existing_lines
                     .iter()
-                    .filter(|code_element| code_element.location.contains(&location))
+                    .map(|line| code_lines[line - 1])
                     .collect();
+                let doc_prefix = if inner { "//!" } else { "///" };
+                let sections = missing_sections(documentation, &existing_text, doc_prefix)?;
+                Some(SourceEdit {
+                    line_start: last_line + 1,
+                    line_end: last_line + 1,
+                    lines: indent(&sections, indentation),
+                })
+            }
+        }
+    };
 
-            for raw_documented_code_element in file_raw_documented_code_elements {
-                // This operation is performed at each iteration to account for previous cycle modifications.
-                let code_elements = parse_file(path)?.elements;
-                let mut code_lines = read_file_to_document_lines(path)?;
+    let mut source_edits: Vec<SourceEdit> = fields_documentation(
+        file,
+        code_lines,
+        &raw_documented_code_element.ident,
+        line_start,
+        &fields_or_variants_descriptions.unwrap_or_default(),
+    )
+    .iter()
+    .filter_map(|(line, field_documentation)| documentation_edit(*line, field_documentation, false))
+    .collect();
+    source_edits.extend(documentation_edit(
+        documentation_start,
+        &documentation,
+        inner,
+    ));
 
-                if let Some(line_start) = find_start(&code_elements, raw_documented_code_element) {
-                    let formatted_documentation =
-                        documentation_formatter(raw_documented_code_element);
-                    code_lines.insert(line_start - 1, formatted_documentation.0);
+    source_edits
+}
 
-                    write_lines_to_file(path, &code_lines)?;
-                }
-            }
+fn indent(documentation: &str, indentation: &str) -> Vec<String> {
+    documentation
+        .lines()
+        .map(|line| format!("{indentation}{}", line.trim_end()))
+        .collect()
+}
+
+fn indentation(code_line: &str) -> &str {
+    &code_line[..code_line.len() - code_line.trim_start().len()]
+}
+
+// Lines, 1-based, of the documentation starting at the line: doc comments and `doc` attributes
+// among the leading attributes and comments of an item, or the inner ones at the top of a module.
+fn documentation_lines<S: AsRef<str>>(
+    code_lines: &[S],
+    line_start: usize,
+    inner: bool,
+) -> Vec<usize> {
+    let (doc_comment, doc_block, attribute) = if inner {
+        ("//!", "/*!", "#![")
+    } else {
+        ("///", "/**", "#[")
+    };
+
+    let mut lines = Vec::new();
+    let mut line = line_start.max(1);
+
+    while let Some(code_line) = code_lines.get(line - 1) {
+        let code_line = code_line.as_ref().trim_start();
+
+        // Blocks, comments and attributes can span several lines.
+        let end_line = |is_end: &dyn Fn(&str) -> bool| {
+            (line..=code_lines.len())
+                .find(|end| is_end(code_lines[end - 1].as_ref()))
+                .unwrap_or(code_lines.len())
+        };
+        let attribute_end = || {
+            let mut depth = 0i32;
+            (line..=code_lines.len())
+                .find(|end| {
+                    let code_line = code_lines[end - 1].as_ref();
+                    depth += code_line.matches('[').count() as i32;
+                    depth -= code_line.matches(']').count() as i32;
+                    depth <= 0
+                })
+                .unwrap_or(code_lines.len())
+        };
+
+        let (is_documentation, end) =
+            if code_line.starts_with(doc_comment) && !code_line.starts_with("////") {
+                (true, line)
+            } else if code_line.starts_with(doc_block) && !code_line.starts_with("/***") {
+                (true, end_line(&|code_line| code_line.contains("*/")))
+            } else if let Some(attribute_content) = code_line.strip_prefix(attribute) {
+                let is_doc = attribute_content.trim_start().starts_with("doc");
+                (is_doc, attribute_end())
+            } else if code_line.starts_with("/*") {
+                (false, end_line(&|code_line| code_line.contains("*/")))
+            } else if code_line.is_empty() || code_line.starts_with("//") {
+                (false, line)
+            } else {
+                break;
+            };
+
+        if is_documentation {
+            lines.extend(line..=end);
         }
+        line = end + 1;
     }
 
-    Ok(())
+    lines
 }
 
-fn read_file_to_document_lines<P: AsRef<std::path::Path>>(file_path: P) -> Result<Vec<String>> {
-    Ok(std::fs::read_to_string(file_path)?
-        .lines()
-        .map(String::from)
-        .collect())
+// Sections, e.g. `# Errors`, of the formatted documentation whose heading is not among the
+// existing documentation lines, to append to them. The prefix is that of the documentation lines,
+// `//!` for modules, as outer and inner doc comments cannot be mixed.
+fn missing_sections(
+    documentation: &str,
+    existing_lines: &[&str],
+    doc_prefix: &str,
+) -> Option<String> {
+    let heading = |line: &str| {
+        let text = line.trim_start().trim_start_matches(['/', '!']).trim();
+        text.starts_with("# ").then(|| text.to_string())
+    };
+    let existing_headings: BTreeSet<String> = existing_lines
+        .iter()
+        .filter_map(|line| heading(line))
+        .collect();
+
+    let mut sections: Vec<Vec<&str>> = Vec::new();
+    for line in documentation.lines() {
+        match (heading(line), sections.last_mut()) {
+            (Some(_), _) => sections.push(vec![line]),
+            (None, Some(section)) => section.push(line),
+            // The general description, already written by hand.
+            (None, None) => (),
+        }
+    }
+
+    let missing: Vec<String> = sections
+        .into_iter()
+        .filter(|section| {
+            heading(section[0]).is_some_and(|heading| !existing_headings.contains(&heading))
+        })
+        .map(|section| {
+            let mut section = section;
+            while section
+                .last()
+                .is_some_and(|line| line.trim_end() == doc_prefix)
+            {
+                section.pop();
+            }
+            format!("{doc_prefix}\n{}", section.join("\n"))
+        })
+        .collect();
+
+    (!missing.is_empty()).then(|| missing.join("\n"))
+}
+
+// Path of the file relative to the root of its repository, as expected by `git apply`, or as
+// given outside of a repository.
+fn patch_label(path: &std::path::Path) -> String {
+    let relative_path = path.canonicalize().ok().and_then(|path| {
+        let toplevel = toplevel(path.parent()?).ok()?.canonicalize().ok()?;
+        path.strip_prefix(toplevel)
+            .ok()
+            .map(|path| path.to_path_buf())
+    });
+
+    // TODO: provide support for non-UTF-8 paths
+    relative_path
+        .as_deref()
+        .unwrap_or(path)
+        .to_str()
+        .unwrap()
+        .trim_start_matches("./")
+        .to_string()
 }
 
 fn write_documentation_to_file<P: AsRef<std::path::Path>>(
@@ -204,8 +1081,3 @@ fn write_documentation_to_file<P: AsRef<std::path::Path>>(
     let json_data = serde_json::to_string_pretty(&raw_documented_code_elements)?;
     Ok(std::fs::write(file_path, json_data)?)
 }
-
-fn write_lines_to_file<P: AsRef<std::path::Path>>(file_path: P, lines: &[String]) -> Result<()> {
-    let modified_code = lines.join("\n");
-    Ok(std::fs::write(file_path, modified_code)?)
-}
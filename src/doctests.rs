@@ -0,0 +1,209 @@
+use crate::{
+    consts::DOC_EXAMPLE_MAX_ERROR_CHARS,
+    manifest::{manifest_dependencies, read_manifest},
+    models::{DependencyKind, ExampleValidation},
+};
+
+use anyhow::{Error, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tokio::process::Command;
+
+// Runs documentation examples as doctests of a crate, built once beforehand. The crate, its normal
+// and dev dependencies, procedural macros included, are available to the examples, as they are to
+// the doctests `cargo test --doc` runs.
+pub(crate) struct ExampleTester {
+    pub(crate) example_validation: ExampleValidation,
+    edition: String,
+    externs: Vec<(String, PathBuf)>,
+    library_paths: BTreeSet<PathBuf>,
+    // Examples are tested concurrently, each from its own file.
+    tested_examples: AtomicUsize,
+}
+
+impl ExampleTester {
+    pub(crate) async fn new(example_validation: &ExampleValidation) -> Result<Self> {
+        let manifest_path = example_validation.manifest_path.canonicalize()?;
+        let manifest = read_manifest(&manifest_path)?;
+
+        // The library itself, then the test harness of the library for the dev dependencies.
+        let mut messages = String::new();
+        for arguments in [&["build", "--lib"][..], &["test", "--lib", "--no-run"]] {
+            let output = Command::new("cargo")
+                .args(arguments)
+                .args(["--offline", "--message-format=json"])
+                .arg("--manifest-path")
+                .arg(&manifest_path)
+                .output()
+                .await?;
+            if !output.status.success() {
+                return Err(Error::msg(format!(
+                    "Building {} to test the examples failed: {}",
+                    manifest_path.display(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            messages += &String::from_utf8_lossy(&output.stdout);
+        }
+
+        // Library names of the packages, with the names the code uses them with.
+        let dependencies: BTreeMap<String, String> = manifest_dependencies(&manifest)
+            .into_iter()
+            .filter(|dependency| dependency.kind != DependencyKind::Build)
+            .map(|dependency| {
+                (
+                    dependency.package.replace('-', "_"),
+                    dependency.crate_name(),
+                )
+            })
+            .collect();
+
+        let mut externs = Vec::new();
+        let mut library_paths = BTreeSet::new();
+
+        for message in messages
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|message| message["reason"] == "compiler-artifact")
+        {
+            // Procedural macros are dynamic libraries.
+            let is_proc_macro = message["target"]["kind"]
+                .as_array()
+                .is_some_and(|kinds| kinds.iter().any(|kind| kind == "proc-macro"));
+            let Some(library) = message["filenames"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|filename| filename.as_str())
+                .find(|filename| {
+                    filename.ends_with(".rlib")
+                        || (is_proc_macro
+                            && [".so", ".dylib", ".dll"]
+                                .iter()
+                                .any(|extension| filename.ends_with(extension)))
+                })
+                .map(PathBuf::from)
+            else {
+                continue;
+            };
+            if let Some(directory) = library.parent() {
+                library_paths.insert(directory.to_path_buf());
+            }
+
+            let Some(name) = message["target"]["name"].as_str() else {
+                continue;
+            };
+            let is_crate = message["manifest_path"]
+                .as_str()
+                .is_some_and(|path| Path::new(path) == manifest_path);
+            let extern_name = if is_crate {
+                name.replace('-', "_")
+            } else if let Some(crate_name) = dependencies.get(name) {
+                crate_name.clone()
+            } else {
+                continue;
+            };
+            // Both builds report the library dependencies, those of the first one are kept.
+            if !externs.iter().any(|(name, _)| name == &extern_name) {
+                externs.push((extern_name, library));
+            }
+        }
+
+        // Editions inherited from the workspace are assumed to be recent.
+        let edition = manifest
+            .get("package")
+            .and_then(|package| package.get("edition"))
+            .map_or("2015", |edition| edition.as_str().unwrap_or("2021"))
+            .to_string();
+
+        Ok(ExampleTester {
+            example_validation: example_validation.clone(),
+            edition,
+            externs,
+            library_paths,
+            tested_examples: AtomicUsize::new(0),
+        })
+    }
+
+    // None when the example succeeds, the output of rustdoc otherwise. Examples without code
+    // blocks are tested as a single one.
+    pub(crate) async fn test(&self, example: &str) -> Result<Option<String>> {
+        let markdown = if example.contains("```") {
+            example.to_string()
+        } else {
+            format!("```\n{example}\n```\n")
+        };
+
+        let markdown_path = std::env::temp_dir().join(format!(
+            "rusty_docs_example_{}_{}.md",
+            std::process::id(),
+            self.tested_examples.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&markdown_path, markdown)?;
+
+        let mut command = Command::new("rustdoc");
+        command
+            .arg("--test")
+            .arg(&markdown_path)
+            .args(["--edition", &self.edition]);
+        for library_path in &self.library_paths {
+            command
+                .arg("-L")
+                .arg(format!("dependency={}", library_path.display()));
+        }
+        for (name, library) in &self.externs {
+            command
+                .arg("--extern")
+                .arg(format!("{name}={}", library.display()));
+        }
+
+        let output = command.output().await;
+        std::fs::remove_file(&markdown_path)?;
+        let output = output?;
+
+        if output.status.success() {
+            return Ok(None);
+        }
+
+        let mut error = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        if let Some((index, _)) = error.char_indices().nth(DOC_EXAMPLE_MAX_ERROR_CHARS) {
+            error.truncate(index);
+        }
+
+        Ok(Some(error))
+    }
+}
+
+// Marks the code blocks of the example `ignore`, so that rustdoc shows them without running them.
+pub(crate) fn mark_untested(example: &str) -> String {
+    if !example.contains("```") {
+        return format!("```ignore\n{example}\n```");
+    }
+
+    let mut in_code_block = false;
+    example
+        .lines()
+        .map(|line| {
+            let fence = line.trim_start();
+            if !fence.starts_with("```") {
+                return line.to_string();
+            }
+
+            in_code_block = !in_code_block;
+            if in_code_block {
+                let indentation = &line[..line.len() - fence.len()];
+                format!("{indentation}```ignore")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
@@ -0,0 +1,391 @@
+use crate::models::{
+    CodeElement, CodeElementID, DocumentedCodeElement, ErrorPropagation, FailureAnalysis, ItemKind,
+    PanicSite, PanicSiteKind,
+};
+
+use std::collections::BTreeMap;
+use syn::{
+    __private::ToTokens,
+    spanned::Spanned,
+    visit::{self, Visit},
+};
+
+const PANIC_MACROS: [&str; 10] = [
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "panic",
+    "todo",
+    "unimplemented",
+    "unreachable",
+];
+
+pub(crate) fn add_failure_analysis(code_elements: &mut [CodeElement]) {
+    for code_element in code_elements
+        .iter_mut()
+        .filter(|code_element| code_element.code_element_id.kind == ItemKind::Fn)
+    {
+        code_element.failure_analysis =
+            failure_analysis(&code_element.code, code_element.line_start);
+    }
+}
+
+// Lines are those of the source file, given the line the function code starts at.
+pub(crate) fn failure_analysis(code: &str, line_start: usize) -> Option<FailureAnalysis> {
+    let (signature, block) = match syn::parse_str::<syn::ItemFn>(code) {
+        Ok(item_fn) => (item_fn.sig, Some(*item_fn.block)),
+        Err(_) => {
+            let trait_item_fn = syn::parse_str::<syn::TraitItemFn>(code).ok()?;
+            (trait_item_fn.sig, trait_item_fn.default)
+        }
+    };
+
+    let mut visitor = FailureVisitor {
+        line_offset: line_start.saturating_sub(1),
+        nested_bodies: 0,
+        analysis: FailureAnalysis {
+            return_type: fallible_return_type(&signature.output),
+            ..Default::default()
+        },
+    };
+    if let Some(block) = &block {
+        visitor.visit_block(block);
+    }
+
+    Some(visitor.analysis)
+}
+
+// Looks for the return types of the propagated calls among the dependencies of each function,
+// then among the imported functions, only when the callee ident is not ambiguous.
+pub(crate) fn resolve_error_propagations(code_elements: &mut [&mut CodeElement]) {
+    let return_types: BTreeMap<CodeElementID, String> = code_elements
+        .iter()
+        .filter_map(|code_element| {
+            Some((
+                code_element.code_element_id.clone(),
+                code_element
+                    .failure_analysis
+                    .as_ref()?
+                    .return_type
+                    .clone()?,
+            ))
+        })
+        .collect();
+
+    for code_element in code_elements.iter_mut() {
+        let Some(failure_analysis) = &mut code_element.failure_analysis else {
+            continue;
+        };
+
+        for error_propagation in &mut failure_analysis.error_propagations {
+            let Some(callee) = &error_propagation.callee else {
+                continue;
+            };
+
+            let mut dependency_return_types = code_element
+                .dependencies
+                .iter()
+                .filter(|code_element_id| &code_element_id.ident == callee)
+                .filter_map(|code_element_id| return_types.get(code_element_id));
+            let mut imported_return_types = return_types.iter().filter(|(code_element_id, _)| {
+                &code_element_id.ident == callee
+                    && code_element.imports.contains(&format!(
+                        "{} :: {}",
+                        code_element_id.location, code_element_id.ident
+                    ))
+            });
+
+            error_propagation.callee_return_type = match (
+                dependency_return_types.next(),
+                dependency_return_types.next(),
+            ) {
+                (Some(return_type), None) => Some(return_type.clone()),
+                (Some(_), Some(_)) => None,
+                _ => match (imported_return_types.next(), imported_return_types.next()) {
+                    (Some((_, return_type)), None) => Some(return_type.clone()),
+                    _ => None,
+                },
+            };
+        }
+    }
+}
+
+// Facts added to the documentation prompt of a function.
+pub(crate) fn failure_analysis_to_prompt(failure_analysis: &FailureAnalysis) -> String {
+    let mut prompt = String::from(
+        "Facts from a static analysis of the function body, what happens inside called functions excluded:\n",
+    );
+
+    if failure_analysis.panic_sites.is_empty() {
+        prompt += "- no panic sites\n";
+    } else {
+        prompt += "- panic sites:\n";
+        for panic_site in &failure_analysis.panic_sites {
+            prompt += &format!(
+                "  - line {}, {}: `{}`\n",
+                panic_site.line, panic_site.kind, panic_site.expression
+            );
+        }
+    }
+
+    if failure_analysis.error_propagations.is_empty() {
+        prompt += "- no errors propagated with `?`\n";
+    } else {
+        prompt += "- errors propagated with `?`:\n";
+        for error_propagation in &failure_analysis.error_propagations {
+            prompt += &format!(
+                "  - line {}: `{}`",
+                error_propagation.line, error_propagation.expression
+            );
+            if let Some(callee_return_type) = &error_propagation.callee_return_type {
+                prompt += &format!(", returning `{callee_return_type}`");
+            }
+            prompt += "\n";
+        }
+    }
+
+    if let Some(return_type) = &failure_analysis.return_type {
+        prompt += &format!("- return type: `{return_type}`\n");
+    }
+
+    prompt + "Base panic_section and error_section on these facts."
+}
+
+// Explicit panics and errors propagated from a function returning a `Result` are certain, the
+// model can only add the others. Sections the model left empty are not forced, as they would only
+// be headings.
+pub(crate) fn apply_failure_analysis(
+    documented_code_element: &mut DocumentedCodeElement,
+    failure_analysis: &FailureAnalysis,
+) {
+    if !documented_code_element.panic_section.trim().is_empty()
+        && failure_analysis.panic_sites.iter().any(|panic_site| {
+            [
+                PanicSiteKind::Expect,
+                PanicSiteKind::Macro,
+                PanicSiteKind::Unwrap,
+            ]
+            .contains(&panic_site.kind)
+        })
+    {
+        documented_code_element.panic_possible = true;
+    }
+
+    let returns_result = failure_analysis
+        .return_type
+        .as_ref()
+        .is_some_and(|return_type| {
+            return_type
+                .split('<')
+                .next()
+                .is_some_and(|path| path.trim().ends_with("Result"))
+        });
+    if returns_result
+        && !failure_analysis.error_propagations.is_empty()
+        && !documented_code_element.error_section.trim().is_empty()
+    {
+        documented_code_element.error_possible = true;
+    }
+}
+
+fn is_arithmetic(bin_op: &syn::BinOp) -> bool {
+    use syn::BinOp::*;
+
+    matches!(
+        bin_op,
+        Add(_)
+            | Sub(_)
+            | Mul(_)
+            | Div(_)
+            | Rem(_)
+            | AddAssign(_)
+            | SubAssign(_)
+            | MulAssign(_)
+            | DivAssign(_)
+            | RemAssign(_)
+    )
+}
+
+fn fallible_return_type(output: &syn::ReturnType) -> Option<String> {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let syn::Type::Path(type_path) = ty.as_ref() else {
+        return None;
+    };
+
+    type_path
+        .path
+        .segments
+        .last()
+        .filter(|segment| ["Option", "Result"].contains(&segment.ident.to_string().as_str()))
+        .map(|_| source_text(ty.as_ref()))
+}
+
+// Source text of a node on a single line, shortened when too long.
+fn source_text<T: Spanned + ToTokens>(node: &T) -> String {
+    let text = node
+        .span()
+        .source_text()
+        .unwrap_or_else(|| node.to_token_stream().to_string());
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if text.chars().count() > 80 {
+        format!("{}...", text.chars().take(77).collect::<String>())
+    } else {
+        text
+    }
+}
+
+struct FailureVisitor {
+    line_offset: usize,
+    // Closures and async blocks being visited, whose `?` do not return from the function.
+    nested_bodies: usize,
+    analysis: FailureAnalysis,
+}
+
+impl FailureVisitor {
+    fn add_panic_site<T: Spanned + ToTokens>(
+        &mut self,
+        node: &T,
+        line: usize,
+        kind: PanicSiteKind,
+    ) {
+        self.analysis.panic_sites.push(PanicSite {
+            line: line + self.line_offset,
+            kind,
+            expression: source_text(node),
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for FailureVisitor {
+    // Strings are concatenated with `+` too, float operations never panic. Chained operations
+    // make a single site.
+    fn visit_expr_binary(&mut self, node: &'ast syn::ExprBinary) {
+        let non_integer = [node.left.as_ref(), node.right.as_ref()]
+            .iter()
+            .any(|operand| {
+                matches!(
+                    operand,
+                    syn::Expr::Reference(_)
+                        | syn::Expr::Macro(_)
+                        | syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(_) | syn::Lit::Float(_),
+                            ..
+                        })
+                )
+            });
+
+        if !is_arithmetic(&node.op) || non_integer {
+            visit::visit_expr_binary(self, node);
+            return;
+        }
+
+        self.add_panic_site(node, node.span().start().line, PanicSiteKind::Arithmetic);
+
+        let mut operands = vec![node.left.as_ref(), node.right.as_ref()];
+        while let Some(mut operand) = operands.pop() {
+            while let syn::Expr::Paren(expr_paren) = operand {
+                operand = expr_paren.expr.as_ref();
+            }
+
+            match operand {
+                syn::Expr::Binary(expr_binary) if is_arithmetic(&expr_binary.op) => {
+                    operands.push(expr_binary.left.as_ref());
+                    operands.push(expr_binary.right.as_ref());
+                }
+                operand => self.visit_expr(operand),
+            }
+        }
+    }
+
+    fn visit_expr_index(&mut self, node: &'ast syn::ExprIndex) {
+        self.add_panic_site(node, node.span().start().line, PanicSiteKind::Indexing);
+        visit::visit_expr_index(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        let kind = match node.method.to_string().as_str() {
+            "unwrap" => Some(PanicSiteKind::Unwrap),
+            "expect" => Some(PanicSiteKind::Expect),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            self.add_panic_site(node, node.method.span().start().line, kind);
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_async(&mut self, node: &'ast syn::ExprAsync) {
+        self.nested_bodies += 1;
+        visit::visit_expr_async(self, node);
+        self.nested_bodies -= 1;
+    }
+
+    fn visit_expr_closure(&mut self, node: &'ast syn::ExprClosure) {
+        self.nested_bodies += 1;
+        visit::visit_expr_closure(self, node);
+        self.nested_bodies -= 1;
+    }
+
+    fn visit_expr_try(&mut self, node: &'ast syn::ExprTry) {
+        if self.nested_bodies > 0 {
+            visit::visit_expr_try(self, node);
+            return;
+        }
+
+        let mut expr = node.expr.as_ref();
+        while let syn::Expr::Await(expr_await) = expr {
+            expr = expr_await.base.as_ref();
+        }
+
+        let callee = match expr {
+            syn::Expr::Call(expr_call) => match expr_call.func.as_ref() {
+                syn::Expr::Path(expr_path) => expr_path
+                    .path
+                    .segments
+                    .last()
+                    .map(|segment| segment.ident.to_string()),
+                _ => None,
+            },
+            syn::Expr::MethodCall(expr_method_call) => Some(expr_method_call.method.to_string()),
+            _ => None,
+        };
+
+        self.analysis.error_propagations.push(ErrorPropagation {
+            line: node.question_token.span().start().line + self.line_offset,
+            expression: source_text(node.expr.as_ref()),
+            callee,
+            callee_return_type: None,
+        });
+        visit::visit_expr_try(self, node);
+    }
+
+    // Nested functions are analyzed on their own, if at all.
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if node
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| PANIC_MACROS.contains(&segment.ident.to_string().as_str()))
+        {
+            self.add_panic_site(node, node.span().start().line, PanicSiteKind::Macro);
+        }
+
+        // Arguments of function-like macros are plain tokens.
+        if let Ok(args) = node.parse_body_with(
+            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+        ) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+        visit::visit_macro(self, node);
+    }
+}
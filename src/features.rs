@@ -0,0 +1,451 @@
+use crate::{
+    manifest::{manifest_dependencies, read_manifest},
+    models::{CodeElement, CodeElementID, CodeFile, Feature, FeatureMatrix, ItemKind, TargetRole},
+};
+
+use anyhow::Result;
+use proc_macro2::{TokenStream, TokenTree};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+use syn::parse::Parser;
+
+// Elements at or under the location. Impl blocks of a type share their location, `impl_Type`,
+// their scope is also restricted to the lines of the block when they are known.
+#[derive(PartialEq)]
+struct Scope {
+    location: String,
+    block: Option<Block>,
+}
+
+#[derive(PartialEq)]
+struct Block {
+    file_index: usize,
+    line_start: usize,
+    line_end: usize,
+}
+
+impl Scope {
+    fn contains(&self, file_index: usize, code_element: &CodeElement) -> bool {
+        let location = &code_element.code_element_id.location;
+        if location != &self.location && !location.starts_with(&format!("{} :: ", self.location)) {
+            return false;
+        }
+
+        match (&self.block, line_range(code_element)) {
+            (Some(block), Some((line_start, line_end))) => {
+                block.file_index == file_index
+                    && block.line_start <= line_start
+                    && line_end <= block.line_end
+            }
+            _ => true,
+        }
+    }
+}
+
+// Features declared in the manifest, with the items gated on each of them.
+pub fn feature_matrix<P: AsRef<Path>, Q: AsRef<Path>>(
+    manifest_path: P,
+    code_files: &[CodeFile<Q>],
+) -> Result<FeatureMatrix> {
+    let manifest = read_manifest(manifest_path)?;
+
+    let mut declared_features: BTreeMap<String, Vec<String>> = manifest
+        .get("features")
+        .and_then(toml::Value::as_table)
+        .map(|features| {
+            features
+                .iter()
+                .map(|(name, enables)| {
+                    let enables = enables
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|enabled| enabled.as_str().map(String::from))
+                        .collect();
+                    (name.clone(), enables)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Optional dependencies are implicit features, unless a feature refers to them with `dep:`.
+    let explicit_dependencies: BTreeSet<&str> = declared_features
+        .values()
+        .flatten()
+        .filter_map(|enabled| enabled.strip_prefix("dep:"))
+        .collect();
+    let implicit_features: Vec<String> = manifest_dependencies(&manifest)
+        .into_iter()
+        .filter(|dependency| {
+            dependency.optional && !explicit_dependencies.contains(dependency.name.as_str())
+        })
+        .map(|dependency| dependency.name)
+        .collect();
+    for dependency in implicit_features {
+        declared_features.entry(dependency).or_default();
+    }
+
+    let default_features = enabled_features(&declared_features, "default");
+
+    let used_features: BTreeSet<&str> = code_files
+        .iter()
+        .flat_map(|code_file| &code_file.elements)
+        .flat_map(|code_element| &code_element.features)
+        .map(String::as_str)
+        .collect();
+
+    let features = declared_features
+        .iter()
+        .map(|(name, enables)| Feature {
+            name: name.clone(),
+            default: default_features.contains(name.as_str()),
+            enables: enables.clone(),
+            gated_elements: top_level_gated_elements(code_files, name),
+        })
+        .collect();
+
+    let undeclared_features = used_features
+        .into_iter()
+        .filter(|feature| !declared_features.contains_key(*feature))
+        .map(|feature| feature.to_string())
+        .collect();
+
+    Ok(FeatureMatrix {
+        features,
+        undeclared_features,
+    })
+}
+
+pub fn feature_matrix_to_markdown(feature_matrix: &FeatureMatrix) -> String {
+    let mut markdown =
+        String::from("# Features\n\n| Feature | Default | Enables |\n|---|---|---|\n");
+
+    for feature in &feature_matrix.features {
+        markdown += &format!(
+            "| `{}` | {} | {} |\n",
+            feature.name,
+            if feature.default { "yes" } else { "no" },
+            feature
+                .enables
+                .iter()
+                .map(|enabled| format!("`{enabled}`"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+
+    for feature in feature_matrix
+        .features
+        .iter()
+        .filter(|feature| !feature.gated_elements.is_empty())
+    {
+        markdown += &format!("\n## `{}`\n\n", feature.name);
+        for code_element_id in &feature.gated_elements {
+            markdown += &format!(
+                "- `{}` ({}) in `{}`\n",
+                code_element_id.ident, code_element_id.kind, code_element_id.location
+            );
+        }
+    }
+
+    if !feature_matrix.undeclared_features.is_empty() {
+        markdown += "\n## Undeclared features\n\n";
+        for feature in &feature_matrix.undeclared_features {
+            markdown += &format!("- `{feature}`\n");
+        }
+    }
+
+    markdown
+}
+
+// Features required by the `cfg` attributes of the element code, `not(...)` predicates aside.
+// `doc(cfg(...))` inside `cfg_attr`, used to document gated items, counts as well.
+pub(crate) fn add_cfg_features(code_elements: &mut [CodeElement]) {
+    let parser = |input: syn::parse::ParseStream| {
+        let attrs = input.call(syn::Attribute::parse_outer)?;
+        input.parse::<TokenStream>()?;
+        Ok(attrs)
+    };
+
+    for code_element in code_elements {
+        let attrs = parser.parse_str(&code_element.code).unwrap_or_default();
+        code_element.features = cfg_features(&attrs);
+    }
+}
+
+pub(crate) fn cfg_features(attrs: &[syn::Attribute]) -> Vec<String> {
+    let mut features = BTreeSet::new();
+
+    for attr in attrs {
+        let syn::Meta::List(meta_list) = &attr.meta else {
+            continue;
+        };
+
+        if meta_list.path.is_ident("cfg") {
+            collect_features(meta_list.tokens.clone(), &mut features);
+        } else if meta_list.path.is_ident("cfg_attr") {
+            for doc_tokens in groups_after(meta_list.tokens.clone(), "doc") {
+                for cfg_tokens in groups_after(doc_tokens, "cfg") {
+                    collect_features(cfg_tokens, &mut features);
+                }
+            }
+        }
+    }
+
+    features.into_iter().collect()
+}
+
+// Gates of modules and impl blocks extend to their content, as do those of `mod` declarations
+// to the files of the declared modules.
+pub(crate) fn add_feature_gates(code_files: &mut [CodeFile<PathBuf>]) {
+    let mut feature_gates = FeatureGates::default();
+
+    for (file_index, code_file) in code_files.iter().enumerate() {
+        feature_gates.add_file(file_index, code_file);
+    }
+
+    feature_gates.apply(code_files);
+}
+
+// Gates collected file by file, the modules of files being read only when their file is added.
+#[derive(Default)]
+pub(crate) struct FeatureGates {
+    gates: Vec<(Scope, BTreeSet<String>)>,
+}
+
+impl FeatureGates {
+    pub(crate) fn add_file<P: AsRef<Path>>(&mut self, file_index: usize, code_file: &CodeFile<P>) {
+        for (index, code_element) in code_file.elements.iter().enumerate() {
+            if index + 1 == code_file.elements.len()
+                && code_element.code_element_id.kind == ItemKind::Mod
+            {
+                for (module, features) in declared_module_gates(code_element) {
+                    self.gates.push((
+                        Scope {
+                            location: module,
+                            block: None,
+                        },
+                        features.into_iter().collect(),
+                    ));
+                }
+            }
+
+            if let Some(scope) = scope(code_file, file_index, index) {
+                if !code_element.features.is_empty() {
+                    self.gates
+                        .push((scope, code_element.features.iter().cloned().collect()));
+                }
+            }
+        }
+    }
+
+    // Files are given in the order they were added.
+    pub(crate) fn apply<P: AsRef<Path>>(&self, code_files: &mut [CodeFile<P>]) {
+        for (file_index, code_file) in code_files.iter_mut().enumerate() {
+            for code_element in &mut code_file.elements {
+                let mut features: BTreeSet<String> =
+                    code_element.features.iter().cloned().collect();
+
+                for (scope, gate) in &self.gates {
+                    if scope.contains(file_index, code_element) {
+                        features.extend(gate.iter().cloned());
+                    }
+                }
+
+                code_element.features = features.into_iter().collect();
+            }
+        }
+    }
+}
+
+fn collect_features(tokens: TokenStream, features: &mut BTreeSet<String>) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match (&tokens[index], tokens.get(index + 1), tokens.get(index + 2)) {
+            (TokenTree::Ident(ident), Some(TokenTree::Group(_)), _) if ident == "not" => {
+                index += 2;
+                continue;
+            }
+            (
+                TokenTree::Ident(ident),
+                Some(TokenTree::Punct(punct)),
+                Some(TokenTree::Literal(literal)),
+            ) if ident == "feature" && punct.as_char() == '=' => {
+                if let Ok(lit_str) =
+                    syn::parse2::<syn::LitStr>(TokenTree::Literal(literal.clone()).into())
+                {
+                    features.insert(lit_str.value());
+                }
+                index += 3;
+                continue;
+            }
+            (TokenTree::Group(group), _, _) => collect_features(group.stream(), features),
+            _ => (),
+        }
+        index += 1;
+    }
+}
+
+// Modules declared with `mod name;` in the file, gated on features.
+fn declared_module_gates(file_module: &CodeElement) -> Vec<(String, Vec<String>)> {
+    let Ok(file) = syn::parse_file(&file_module.code) else {
+        return Vec::new();
+    };
+
+    let location = &file_module.code_element_id.location;
+    let segments: Vec<&str> = location.split(" :: ").collect();
+    // Files of crate roots declare modules at the root of the location.
+    let parent_location = match file_module.code_element_id.role() {
+        TargetRole::Library if ["crate :: lib", "crate :: main"].contains(&location.as_str()) => {
+            segments[0].to_string()
+        }
+        // e.g. `dependency :: serde :: lib`
+        TargetRole::Dependency if segments.len() == 3 && segments[2] == "lib" => {
+            segments[..2].join(" :: ")
+        }
+        TargetRole::Library | TargetRole::Dependency => location.clone(),
+        _ if segments.len() == 2 => segments[0].to_string(),
+        _ => location.clone(),
+    };
+
+    file.items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Mod(item_mod) if item_mod.content.is_none() => {
+                let features = cfg_features(&item_mod.attrs);
+                (!features.is_empty())
+                    .then(|| (format!("{parent_location} :: {}", item_mod.ident), features))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Transitive closure of the features enabled by a feature.
+fn enabled_features<'a>(
+    declared_features: &'a BTreeMap<String, Vec<String>>,
+    feature: &'a str,
+) -> BTreeSet<&'a str> {
+    let mut enabled = BTreeSet::new();
+    let mut to_visit = vec![feature];
+
+    while let Some(feature) = to_visit.pop() {
+        if let Some(enables) = declared_features.get(feature) {
+            for enabled_feature in enables {
+                if enabled.insert(enabled_feature.as_str()) {
+                    to_visit.push(enabled_feature);
+                }
+            }
+        }
+    }
+
+    enabled
+}
+
+fn groups_after(tokens: TokenStream, ident: &str) -> Vec<TokenStream> {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+
+    tokens
+        .windows(2)
+        .filter_map(|window| match window {
+            [TokenTree::Ident(found), TokenTree::Group(group)] if found == ident => {
+                Some(group.stream())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Content of a module, impl block or trait. The module representing the file is the last element
+// of a CodeFile, its content shares its location.
+fn scope<P: AsRef<Path>>(
+    code_file: &CodeFile<P>,
+    file_index: usize,
+    index: usize,
+) -> Option<Scope> {
+    let code_element = &code_file.elements[index];
+    let code_element_id = &code_element.code_element_id;
+
+    let location = match code_element_id.kind {
+        ItemKind::Mod if index + 1 == code_file.elements.len() => code_element_id.location.clone(),
+        ItemKind::Impl | ItemKind::Mod | ItemKind::Trait => {
+            format!("{} :: {}", code_element_id.location, code_element_id.ident)
+        }
+        _ => return None,
+    };
+    let block = match code_element_id.kind {
+        ItemKind::Impl => line_range(code_element).map(|(line_start, line_end)| Block {
+            file_index,
+            line_start,
+            line_end,
+        }),
+        _ => None,
+    };
+
+    Some(Scope { location, block })
+}
+
+// Lines of the element in its file, unknown for elements read back without their span.
+fn line_range(code_element: &CodeElement) -> Option<(usize, usize)> {
+    match &code_element.span {
+        Some(span) => Some((span.line_start, span.line_end)),
+        None if code_element.line_start > 0 => Some((
+            code_element.line_start,
+            code_element.line_start + code_element.code.lines().count().saturating_sub(1),
+        )),
+        None => None,
+    }
+}
+// Elements gated on the feature, without those whose gate comes from an enclosing scope.
+fn top_level_gated_elements<P: AsRef<Path>>(
+    code_files: &[CodeFile<P>],
+    feature: &str,
+) -> Vec<CodeElementID> {
+    let gated_scopes: Vec<Scope> = code_files
+        .iter()
+        .enumerate()
+        .flat_map(|(file_index, code_file)| {
+            (0..code_file.elements.len()).filter_map(move |index| {
+                code_file.elements[index]
+                    .features
+                    .iter()
+                    .any(|gate| gate == feature)
+                    .then(|| scope(code_file, file_index, index))
+                    .flatten()
+            })
+        })
+        .collect();
+
+    let mut gated_elements: Vec<CodeElementID> = code_files
+        .iter()
+        .enumerate()
+        .flat_map(|(file_index, code_file)| {
+            let gated_scopes = &gated_scopes;
+
+            (0..code_file.elements.len()).filter_map(move |index| {
+                let code_element = &code_file.elements[index];
+                let own_scope = scope(code_file, file_index, index);
+
+                // `mod name;` declarations are represented by the module of their file.
+                let declaration = index + 1 != code_file.elements.len()
+                    && code_element.code_element_id.kind == ItemKind::Mod
+                    && code_element.code.trim_end().ends_with(';');
+                let listed_elsewhere = declaration
+                    || gated_scopes.iter().any(|gated_scope| {
+                        Some(gated_scope) != own_scope.as_ref()
+                            && gated_scope.contains(file_index, code_element)
+                    });
+
+                (code_element.features.iter().any(|gate| gate == feature) && !listed_elsewhere)
+                    .then(|| code_element.code_element_id.clone())
+            })
+        })
+        .collect();
+
+    gated_elements.sort();
+    gated_elements
+}
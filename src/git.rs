@@ -0,0 +1,134 @@
+use anyhow::{Error, Result};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+// Runs a git command inside the given directory and returns its standard output.
+fn git<P: AsRef<Path>>(directory_path: P, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory_path.as_ref())
+        .args(args)
+        .output()?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(Error::msg(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+pub(crate) fn list_files<P: AsRef<Path>>(
+    directory_path: P,
+    commit_id: &str,
+) -> Result<Vec<PathBuf>> {
+    // Paths are relative to the directory, and limited to its content.
+    let stdout = git(
+        &directory_path,
+        &["ls-tree", "-r", "-z", "--name-only", commit_id],
+    )?;
+
+    Ok(stdout
+        .split(|byte| *byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+        .collect())
+}
+
+// A single `git cat-file --batch` process reading the files of a commit one after the other,
+// instead of one process per file.
+pub(crate) struct FileReader {
+    process: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl FileReader {
+    pub(crate) fn new<P: AsRef<Path>>(directory_path: P) -> Result<Self> {
+        let mut process = Command::new("git")
+            .arg("-C")
+            .arg(directory_path.as_ref())
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            return Err(Error::msg("git cat-file --batch cannot be read from"));
+        };
+
+        Ok(Self {
+            process,
+            stdin: Some(stdin),
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    // Paths are relative to the directory the reader was started in.
+    pub(crate) fn read_file(&mut self, commit_id: &str, relative_path: &Path) -> Result<String> {
+        // TODO: provide support for non-UTF-8 paths
+        let object = format!("{commit_id}:./{}", relative_path.to_str().unwrap());
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| Error::msg("git cat-file --batch is closed"))?;
+        writeln!(stdin, "{object}")?;
+        stdin.flush()?;
+
+        // `<oid> <type> <size>` followed by the content and a newline, or `<object> missing` for
+        // unknown objects.
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        let fields: Vec<&str> = header.trim_end().rsplitn(3, ' ').collect();
+        let (kind, size) = match fields[..] {
+            [size, kind, _] if size.parse::<usize>().is_ok() => (kind, size.parse::<usize>()?),
+            _ => {
+                return Err(Error::msg(format!(
+                    "git cat-file --batch failed for {object}: {}",
+                    header.trim()
+                )))
+            }
+        };
+
+        let mut content = vec![0; size + 1];
+        self.stdout.read_exact(&mut content)?;
+        content.pop();
+
+        if kind != "blob" {
+            return Err(Error::msg(format!("{object} is a {kind}, not a file")));
+        }
+
+        Ok(String::from_utf8(content)?)
+    }
+}
+
+impl Drop for FileReader {
+    // Closing its input ends the process.
+    fn drop(&mut self) {
+        self.stdin.take();
+        let _ = self.process.wait();
+    }
+}
+
+pub(crate) fn resolve_commit<P: AsRef<Path>>(directory_path: P, revision: &str) -> Result<String> {
+    let stdout = git(
+        directory_path,
+        &["rev-parse", "--verify", &format!("{revision}^{{commit}}")],
+    )?;
+
+    Ok(String::from_utf8(stdout)?.trim().to_string())
+}
+
+// Root of the working tree the directory belongs to.
+pub(crate) fn toplevel<P: AsRef<Path>>(directory_path: P) -> Result<PathBuf> {
+    let stdout = git(directory_path, &["rev-parse", "--show-toplevel"])?;
+
+    Ok(PathBuf::from(String::from_utf8(stdout)?.trim()))
+}
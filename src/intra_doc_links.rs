@@ -0,0 +1,324 @@
+use crate::{
+    markdown::reference_matches,
+    models::{CodeElement, CodeElementID, DocumentedCodeElement, ItemKind},
+};
+
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{Item, Visibility};
+
+// Names rustdoc resolves on its own or that are not items, left as code spans.
+const UNLINKED_NAMES: [&str; 38] = [
+    "Box", "Err", "None", "Ok", "Option", "Result", "Self", "Some", "String", "Vec", "bool",
+    "char", "crate", "f32", "f64", "false", "i128", "i16", "i32", "i64", "i8", "isize", "mut",
+    "self", "str", "super", "true", "u128", "u16", "u32", "u64", "u8", "unsafe", "usize", "async",
+    "await", "dyn", "impl",
+];
+
+// Resolves the names written between backticks in generated documentation against the parsed
+// elements, to turn them into intra-doc links.
+pub(crate) struct IntraDocLinker<'a> {
+    code_elements: BTreeMap<&'a str, Vec<&'a CodeElementID>>,
+    // Locations of the items of impl blocks and traits.
+    associated_scopes: BTreeSet<String>,
+    // Elements declared without `pub`, which are not part of the public documentation.
+    private_elements: BTreeSet<&'a CodeElementID>,
+    reference: Regex,
+}
+
+// How a name resolved to a single element is written.
+enum Link {
+    // The name alone, e.g. [`Foo`], as it is in scope of the documented element.
+    InScope,
+    Path(String),
+    // Left as a code span, public documentation linking to a private item making rustdoc warn.
+    Private,
+}
+
+impl<'a> IntraDocLinker<'a> {
+    pub(crate) fn new(code_elements: &[&'a CodeElement]) -> Self {
+        let mut linkable_elements: BTreeMap<&str, Vec<&CodeElementID>> = BTreeMap::new();
+        let mut associated_scopes = BTreeSet::new();
+
+        // Items of traits share the visibility of the trait.
+        let trait_scopes: BTreeSet<String> = code_elements
+            .iter()
+            .map(|code_element| &code_element.code_element_id)
+            .filter(|code_element_id| code_element_id.kind == ItemKind::Trait)
+            .map(|code_element_id| {
+                format!("{} :: {}", code_element_id.location, code_element_id.ident)
+            })
+            .collect();
+        let private_elements = code_elements
+            .iter()
+            .filter(|code_element| {
+                !trait_scopes.contains(&code_element.code_element_id.location)
+                    && !is_public(code_element)
+            })
+            .map(|code_element| &code_element.code_element_id)
+            .collect();
+
+        for code_element in code_elements {
+            let code_element_id = &code_element.code_element_id;
+
+            match code_element_id.kind {
+                ItemKind::Impl | ItemKind::Trait => {
+                    associated_scopes.insert(format!(
+                        "{} :: {}",
+                        code_element_id.location, code_element_id.ident
+                    ));
+                }
+                _ => (),
+            }
+            if [
+                ItemKind::DocSection,
+                ItemKind::ExternCrate,
+                ItemKind::ForeignMod,
+                ItemKind::Impl,
+                ItemKind::Use,
+                ItemKind::Verbatim,
+            ]
+            .contains(&code_element_id.kind)
+            {
+                continue;
+            }
+
+            linkable_elements
+                .entry(code_element_id.ident.as_str())
+                .or_default()
+                .push(code_element_id);
+        }
+
+        IntraDocLinker {
+            code_elements: linkable_elements,
+            associated_scopes,
+            private_elements,
+            reference: Regex::new(r"^[A-Za-z_]\w*(?:::[A-Za-z_]\w*)*(?:\(\)|!)?$").unwrap(),
+        }
+    }
+
+    // Rewrites the text sections and field descriptions of the documentation, returning the names
+    // that could not be resolved to a single element.
+    pub(crate) fn link(
+        &self,
+        raw_documented_code_element: &mut DocumentedCodeElement,
+        documented_id: &CodeElementID,
+    ) -> Vec<String> {
+        let mut unresolved = BTreeSet::new();
+        let documented = (
+            documented_id,
+            !self.private_elements.contains(documented_id),
+        );
+
+        for text in [
+            &mut raw_documented_code_element.general_description,
+            &mut raw_documented_code_element.panic_section,
+            &mut raw_documented_code_element.error_section,
+            &mut raw_documented_code_element.safety_section,
+        ] {
+            *text = self.link_text(text, documented, &mut unresolved);
+        }
+        for (_, description) in raw_documented_code_element
+            .fields_or_variants_descriptions
+            .iter_mut()
+            .flatten()
+        {
+            *description = self.link_text(description, documented, &mut unresolved);
+        }
+
+        unresolved.into_iter().collect()
+    }
+
+    // Code spans already inside links and code blocks are left as they are.
+    fn link_text(
+        &self,
+        text: &str,
+        documented: (&CodeElementID, bool),
+        unresolved: &mut BTreeSet<String>,
+    ) -> String {
+        let mut fenced = false;
+
+        text.split('\n')
+            .map(|line| {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    fenced = !fenced;
+                }
+                if fenced || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    return line.to_string();
+                }
+
+                let parts: Vec<&str> = line.split('`').collect();
+                let mut linked_line = String::new();
+
+                for (index, part) in parts.iter().enumerate() {
+                    // Odd parts are code spans when closed.
+                    let is_code_span = index % 2 == 1 && index + 1 < parts.len();
+                    if !is_code_span {
+                        if index % 2 == 1 {
+                            linked_line.push('`');
+                        }
+                        linked_line += part;
+                        continue;
+                    }
+
+                    let in_link = parts[index - 1].ends_with('[');
+                    match self.resolve(part, documented) {
+                        Some(_) | None if in_link => linked_line += &format!("`{part}`"),
+                        Some(Link::InScope) => linked_line += &format!("[`{part}`]"),
+                        Some(Link::Path(target)) => linked_line += &format!("[`{part}`]({target})"),
+                        Some(Link::Private) => linked_line += &format!("`{part}`"),
+                        None => {
+                            if self.is_name(part) && !is_unlinked(part) {
+                                unresolved.insert(part.trim().to_string());
+                            }
+                            linked_line += &format!("`{part}`");
+                        }
+                    }
+                }
+
+                linked_line
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn is_name(&self, reference: &str) -> bool {
+        self.reference.is_match(&reference.replace(' ', ""))
+    }
+
+    // None when the name is not that of a single element. Documentation of public elements, given
+    // with the documented element, does not link to private ones.
+    fn resolve(
+        &self,
+        reference: &str,
+        (documented_id, documented_public): (&CodeElementID, bool),
+    ) -> Option<Link> {
+        if !self.is_name(reference) || is_unlinked(reference) {
+            return None;
+        }
+
+        let reference = reference.replace(' ', "");
+        let (path, suffix) = match reference.strip_suffix("()") {
+            Some(path) => (path, "()"),
+            None => match reference.strip_suffix('!') {
+                Some(path) => (path, "!"),
+                None => (reference.as_str(), ""),
+            },
+        };
+        let ident = path.rsplit("::").next()?;
+
+        let candidates: Vec<&CodeElementID> = self
+            .code_elements
+            .get(ident)?
+            .iter()
+            .copied()
+            .filter(|code_element_id| reference_matches(path, code_element_id))
+            .collect();
+        let hidden = |code_element_id: &CodeElementID| {
+            documented_public && self.private_elements.contains(code_element_id)
+        };
+
+        // Names of the module of the documented element are in scope, those of its impl block or
+        // trait through `Self`.
+        if let Some(code_element_id) = candidates
+            .iter()
+            .find(|code_element_id| code_element_id.location == documented_id.location)
+        {
+            return Some(if hidden(code_element_id) {
+                Link::Private
+            } else if self.associated_scopes.contains(&code_element_id.location) {
+                Link::Path(format!("Self::{ident}{suffix}"))
+            } else {
+                Link::InScope
+            });
+        }
+
+        let targets: BTreeMap<String, &CodeElementID> = candidates
+            .iter()
+            .filter_map(|code_element_id| {
+                self.rust_path(code_element_id, documented_id)
+                    .map(|target| (target, *code_element_id))
+            })
+            .collect();
+        match targets.into_iter().collect::<Vec<_>>().as_slice() {
+            [(_, code_element_id)] if hidden(code_element_id) => Some(Link::Private),
+            [(target, _)] => Some(Link::Path(format!("{target}{suffix}"))),
+            _ => None,
+        }
+    }
+
+    // Path of the element from the documented one, e.g. `crate::models::CodeElement`, None for
+    // those of other targets, which documentation cannot link to.
+    fn rust_path(
+        &self,
+        code_element_id: &CodeElementID,
+        documented_id: &CodeElementID,
+    ) -> Option<String> {
+        let segments: Vec<&str> = code_element_id.location.split(" :: ").collect();
+        let documented_segments: Vec<&str> = documented_id.location.split(" :: ").collect();
+
+        // Root of the path and index of the segment following the crate.
+        let (root, start) = match segments.as_slice() {
+            ["crate", ..] if documented_segments[0] == "crate" => ("crate".to_string(), 1),
+            ["workspace", _, ..] if documented_segments[..2] == segments[..2] => {
+                ("crate".to_string(), 2)
+            }
+            ["workspace" | "dependency", name, ..] => (name.to_string(), 2),
+            _ => return None,
+        };
+
+        let mut path = vec![root];
+        for (index, segment) in segments.iter().enumerate().skip(start) {
+            // Items of the crate root are located in `lib` or `main`, those of `mod.rs` files in
+            // `mod`.
+            if (index == start && ["lib", "main"].contains(segment)) || segment == &"mod" {
+                continue;
+            }
+            path.push(segment.strip_prefix("impl_").unwrap_or(segment).to_string());
+        }
+
+        // The module of a file is located at its own path.
+        let file_module = code_element_id.kind == ItemKind::Mod
+            && segments.last() == Some(&code_element_id.ident.as_str());
+        if !file_module {
+            path.push(code_element_id.ident.clone());
+        }
+
+        let path = path.join("::");
+        self.is_name(&path).then_some(path)
+    }
+}
+
+// Modules of files and elements whose code does not parse as an item, e.g. macros, are taken as
+// public. Items of trait impls, declared without `pub`, are taken as private, as they cannot be
+// told from those of inherent impls.
+fn is_public(code_element: &CodeElement) -> bool {
+    let code_element_id = &code_element.code_element_id;
+    if code_element_id.kind == ItemKind::Mod
+        && code_element_id.location.rsplit(" :: ").next() == Some(code_element_id.ident.as_str())
+    {
+        return true;
+    }
+
+    let visibility = match syn::parse_str::<Item>(&code_element.code) {
+        Ok(Item::Const(item)) => item.vis,
+        Ok(Item::Enum(item)) => item.vis,
+        Ok(Item::Fn(item)) => item.vis,
+        Ok(Item::Mod(item)) => item.vis,
+        Ok(Item::Static(item)) => item.vis,
+        Ok(Item::Struct(item)) => item.vis,
+        Ok(Item::Trait(item)) => item.vis,
+        Ok(Item::TraitAlias(item)) => item.vis,
+        Ok(Item::Type(item)) => item.vis,
+        Ok(Item::Union(item)) => item.vis,
+        _ => return true,
+    };
+
+    matches!(visibility, Visibility::Public(_))
+}
+
+fn is_unlinked(reference: &str) -> bool {
+    let reference = reference.trim();
+    UNLINKED_NAMES.contains(&reference.split("::").next().unwrap_or(reference))
+}
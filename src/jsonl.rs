@@ -0,0 +1,220 @@
+use crate::{
+    features::FeatureGates,
+    models::{CodeElement, CodeFile, ItemKind, ParsedRevision},
+    parsing::{parse_repository_file, parsing_step_2},
+    source::{FsSourceProvider, SourceProvider},
+};
+
+use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+// One line of a JSON Lines file: a CodeElement together with the path of the file it belongs to.
+#[derive(Debug, Deserialize)]
+pub struct CodeElementRecord {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub element: CodeElement,
+}
+
+// First line of the JSON Lines files of parsed revisions, followed by the records of the elements.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RevisionRecord {
+    pub commit_id: String,
+    pub revision: String,
+}
+
+#[derive(Serialize)]
+struct CodeElementRecordRef<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    element: &'a CodeElement,
+}
+
+// Records are written as soon as their file is parsed, only the elements needed by the link pass
+// being kept in memory, without the sources of the modules. The fields set by the link pass are
+// then filled in while copying the records to the JSON Lines file.
+pub fn parse_repository_to_jsonl<P: AsRef<Path>, W: AsRef<Path>>(
+    repository_path: P,
+    jsonl_path: W,
+    store_spans: bool,
+) -> Result<usize> {
+    let repository_path = repository_path.as_ref();
+    let jsonl_path = jsonl_path.as_ref();
+    let mut parsed_path = jsonl_path.as_os_str().to_owned();
+    parsed_path.push(".parsed");
+    let parsed_path = PathBuf::from(parsed_path);
+
+    let mut code_files = Vec::new();
+    let mut feature_gates = FeatureGates::default();
+    let mut writer = BufWriter::new(std::fs::File::create(&parsed_path)?);
+
+    for path in FsSourceProvider.list_files(repository_path)? {
+        let Some(mut code_file) = parse_repository_file(&FsSourceProvider, repository_path, path)?
+        else {
+            continue;
+        };
+
+        feature_gates.add_file(code_files.len(), &code_file);
+        if store_spans {
+            strip_module_sources(&mut code_file);
+        }
+        write_records(&mut writer, std::slice::from_ref(&code_file))?;
+
+        // Modules carry the whole source of their file and are never searched for dependencies.
+        strip_module_sources(&mut code_file);
+        code_files.push(code_file);
+    }
+    writer.flush()?;
+    drop(writer);
+
+    feature_gates.apply(&mut code_files);
+    let code_files = parsing_step_2(code_files);
+
+    let mut writer = BufWriter::new(std::fs::File::create(jsonl_path)?);
+    let mut linked_elements = code_files.iter().flat_map(|code_file| &code_file.elements);
+    let mut written = 0;
+
+    for record in read_jsonl(&parsed_path)? {
+        let CodeElementRecord { path, mut element } = record?;
+        let linked_element = linked_elements
+            .next()
+            .ok_or_else(|| Error::msg("More parsed records than linked elements"))?;
+
+        element.features = linked_element.features.clone();
+        element.dependencies = linked_element.dependencies.clone();
+        element.implementors = linked_element.implementors.clone();
+        element.failure_analysis = linked_element.failure_analysis.clone();
+
+        serde_json::to_writer(
+            &mut writer,
+            &CodeElementRecordRef {
+                path: &path,
+                element: &element,
+            },
+        )?;
+        writer.write_all(b"\n")?;
+        written += 1;
+    }
+    writer.flush()?;
+    std::fs::remove_file(&parsed_path)?;
+
+    Ok(written)
+}
+
+// The revision record, when there is one, is skipped.
+pub fn read_jsonl<P: AsRef<Path>>(
+    jsonl_path: P,
+) -> Result<impl Iterator<Item = Result<CodeElementRecord>>> {
+    let reader = BufReader::new(std::fs::File::open(jsonl_path)?);
+
+    Ok(reader
+        .lines()
+        .enumerate()
+        .filter(|(index, line)| {
+            line.as_ref().map_or(true, |line| {
+                let revision_record =
+                    *index == 0 && serde_json::from_str::<RevisionRecord>(line).is_ok();
+                !line.trim().is_empty() && !revision_record
+            })
+        })
+        .map(|(_, line)| Ok(serde_json::from_str(&line?)?)))
+}
+
+// None for files of the working tree.
+pub fn read_jsonl_revision<P: AsRef<Path>>(jsonl_path: P) -> Result<Option<RevisionRecord>> {
+    let mut first_line = String::new();
+    BufReader::new(std::fs::File::open(jsonl_path)?).read_line(&mut first_line)?;
+
+    Ok(serde_json::from_str(&first_line).ok())
+}
+
+// Groups the records back into CodeFiles, restoring the code of the elements stored as spans.
+pub fn read_jsonl_to_code_files<P: AsRef<Path>>(jsonl_path: P) -> Result<Vec<CodeFile<PathBuf>>> {
+    let mut code_files: Vec<CodeFile<PathBuf>> = Vec::new();
+
+    for record in read_jsonl(jsonl_path)? {
+        let CodeElementRecord { path, mut element } = record?;
+
+        if element.span.is_some() {
+            element.resolve_code(&std::fs::read_to_string(&path)?)?;
+        }
+
+        match code_files.last_mut() {
+            Some(code_file) if code_file.path == path => code_file.elements.push(element),
+            _ => code_files.push(CodeFile {
+                path,
+                elements: vec![element],
+            }),
+        }
+    }
+
+    Ok(code_files)
+}
+
+pub fn write_jsonl<P: AsRef<Path>, W: AsRef<Path>>(
+    code_files: &[CodeFile<P>],
+    jsonl_path: W,
+) -> Result<usize> {
+    let mut writer = BufWriter::new(std::fs::File::create(jsonl_path)?);
+    let written = write_records(&mut writer, code_files)?;
+    writer.flush()?;
+
+    Ok(written)
+}
+
+// The commit is written first, as a revision record.
+pub(crate) fn write_revision_jsonl<W: AsRef<Path>>(
+    parsed_revision: &ParsedRevision,
+    jsonl_path: W,
+) -> Result<usize> {
+    let mut writer = BufWriter::new(std::fs::File::create(jsonl_path)?);
+    serde_json::to_writer(
+        &mut writer,
+        &RevisionRecord {
+            commit_id: parsed_revision.commit_id.clone(),
+            revision: parsed_revision.revision.clone(),
+        },
+    )?;
+    writer.write_all(b"\n")?;
+    let written = write_records(&mut writer, &parsed_revision.code_files)?;
+    writer.flush()?;
+
+    Ok(written)
+}
+
+fn write_records<P: AsRef<Path>, W: Write>(
+    writer: &mut W,
+    code_files: &[CodeFile<P>],
+) -> Result<usize> {
+    let mut written = 0;
+
+    for code_file in code_files {
+        for code_element in &code_file.elements {
+            serde_json::to_writer(
+                &mut *writer,
+                &CodeElementRecordRef {
+                    path: code_file.path.as_ref(),
+                    element: code_element,
+                },
+            )?;
+            writer.write_all(b"\n")?;
+            written += 1;
+        }
+    }
+
+    Ok(written)
+}
+
+fn strip_module_sources(code_file: &mut CodeFile<PathBuf>) {
+    for code_element in &mut code_file.elements {
+        if code_element.code_element_id.kind == ItemKind::Mod {
+            // Inline modules have line_start moved to their first inner line.
+            let line_start = code_element.line_start.saturating_sub(1).max(1);
+            code_element.strip_code(line_start);
+        }
+    }
+}
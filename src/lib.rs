@@ -1,7 +1,30 @@
+pub mod api;
 mod consts;
+pub mod crates;
+pub mod dependencies;
+pub mod diff;
 pub mod doc_gen;
+mod doctests;
+mod failures;
+pub mod features;
+mod git;
+mod intra_doc_links;
+pub mod jsonl;
+mod linking;
+mod manifest;
+mod markdown;
+pub mod metrics;
 pub mod models;
 pub mod openai;
 pub mod parsing;
+mod patch;
 pub mod qdrant;
+pub mod rate_limit;
+pub mod rustdoc;
+pub mod source;
+mod style;
+mod targets;
+pub mod templates;
+pub mod unsafety;
 mod utils;
+pub mod workspace;
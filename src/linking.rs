@@ -0,0 +1,182 @@
+use crate::{
+    crates::{code_idents, imported_crate, imported_name},
+    models::{CodeElementID, CodeFile, ItemKind},
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+// Re-exports followed when resolving an import, e.g. from a `prelude` module.
+const MAX_REEXPORT_DEPTH: usize = 4;
+
+// Items of crates located under a namespace, e.g. `dependency :: serde :: de` for the `de` module
+// of the `serde` dependency, that imports of other crates can be resolved against.
+pub(crate) struct NamespacedItems {
+    namespace: String,
+    crates: BTreeSet<String>,
+    elements: BTreeMap<(String, String), CodeElementID>,
+    // Imports of the modules, re-exports included.
+    module_imports: BTreeMap<String, Vec<String>>,
+}
+
+impl NamespacedItems {
+    pub(crate) fn new(code_files: &[CodeFile<PathBuf>], namespace: &str) -> Self {
+        let prefix = format!("{namespace} :: ");
+        let code_elements = code_files
+            .iter()
+            .flat_map(|code_file| &code_file.elements)
+            .filter(|code_element| code_element.code_element_id.location.starts_with(&prefix));
+
+        let elements: BTreeMap<(String, String), CodeElementID> = code_elements
+            .clone()
+            .filter(|code_element| {
+                ![ItemKind::Impl, ItemKind::Mod, ItemKind::Use]
+                    .contains(&code_element.code_element_id.kind)
+            })
+            .map(|code_element| {
+                let code_element_id = &code_element.code_element_id;
+                (
+                    (
+                        code_element_id.location.clone(),
+                        code_element_id.ident.clone(),
+                    ),
+                    code_element_id.clone(),
+                )
+            })
+            .collect();
+
+        Self {
+            namespace: namespace.to_string(),
+            crates: elements
+                .keys()
+                .filter_map(|(location, _)| location.split(" :: ").nth(1))
+                .map(String::from)
+                .collect(),
+            elements,
+            module_imports: code_files
+                .iter()
+                .filter_map(|code_file| code_file.elements.last())
+                .filter(|code_element| {
+                    code_element.code_element_id.kind == ItemKind::Mod
+                        && code_element.code_element_id.location.starts_with(&prefix)
+                })
+                .map(|code_element| {
+                    (
+                        code_element.code_element_id.location.clone(),
+                        code_element.imports.clone(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    // Adds the items the elements refer to through their imports to their dependencies. The
+    // elements to add to the implementors of each item are returned.
+    pub(crate) fn link_imports(
+        &self,
+        code_files: &mut [CodeFile<PathBuf>],
+    ) -> BTreeMap<CodeElementID, Vec<CodeElementID>> {
+        let mut implementors: BTreeMap<CodeElementID, Vec<CodeElementID>> = BTreeMap::new();
+
+        for code_element in code_files
+            .iter_mut()
+            .flat_map(|code_file| &mut code_file.elements)
+            .filter(|code_element| code_element.code_element_id.kind != ItemKind::DocSection)
+        {
+            let idents = code_idents(&code_element.code);
+            let linked: Vec<CodeElementID> = code_element
+                .imports
+                .iter()
+                .filter(|import| {
+                    imported_crate(import).is_some_and(|name| self.crates.contains(name))
+                        && idents.contains(imported_name(import))
+                })
+                .filter_map(|import| {
+                    self.resolve_import(
+                        &format!(
+                            "{} :: {}",
+                            self.namespace,
+                            import.trim_start_matches(" :: ")
+                        ),
+                        MAX_REEXPORT_DEPTH,
+                    )
+                })
+                .filter(|code_element_id| code_element_id != &code_element.code_element_id)
+                .collect();
+
+            for code_element_id in linked {
+                implementors
+                    .entry(code_element_id.clone())
+                    .or_default()
+                    .push(code_element.code_element_id.clone());
+                code_element.dependencies.push(code_element_id);
+            }
+
+            code_element.dependencies.sort();
+            code_element.dependencies.dedup();
+        }
+
+        implementors
+    }
+
+    // Looks for the item among the elements, then among the imports of the module the path
+    // refers to, then for a single item with the same ident in the crate.
+    fn resolve_import(&self, import: &str, depth: usize) -> Option<CodeElementID> {
+        let path = import.split(" as ").next()?;
+        let (module, ident) = path.rsplit_once(" :: ")?;
+        let segments: Vec<&str> = module.split(" :: ").collect();
+        // Items at the root of a crate are located in its `lib` file.
+        let module = if segments.len() == 2 {
+            format!("{module} :: lib")
+        } else {
+            module.to_string()
+        };
+
+        if let Some(code_element_id) = self.elements.get(&(module.clone(), ident.to_string())) {
+            return Some(code_element_id.clone());
+        }
+
+        if depth > 0 {
+            if let Some(reexport) = self.module_imports.get(&module).and_then(|imports| {
+                imports.iter().find(|import| {
+                    import.starts_with(&format!("{} :: ", self.namespace))
+                        && imported_name(import) == ident
+                })
+            }) {
+                if let Some(code_element_id) = self.resolve_import(reexport, depth - 1) {
+                    return Some(code_element_id);
+                }
+            }
+        }
+
+        let crate_root = segments.get(..2)?.join(" :: ");
+        let mut same_ident = self
+            .elements
+            .iter()
+            .filter(|((location, element_ident), _)| {
+                element_ident == ident && location.starts_with(&format!("{crate_root} :: "))
+            });
+        match (same_ident.next(), same_ident.next()) {
+            (Some((_, code_element_id)), None) => Some(code_element_id.clone()),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn add_linked_implementors(
+    code_files: &mut [CodeFile<PathBuf>],
+    implementors: &BTreeMap<CodeElementID, Vec<CodeElementID>>,
+) {
+    for code_element in code_files
+        .iter_mut()
+        .flat_map(|code_file| &mut code_file.elements)
+    {
+        if let Some(linked) = implementors.get(&code_element.code_element_id) {
+            code_element.implementors.extend(linked.iter().cloned());
+            code_element.implementors.sort();
+            code_element.implementors.dedup();
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::models::DependencyKind;
+
+use anyhow::Result;
+use std::{collections::BTreeMap, path::Path};
+
+// A dependency as declared in Cargo.toml, target specific ones included.
+pub(crate) struct ManifestDependency {
+    // Key of the dependency, the name used in code once dashes are replaced.
+    pub(crate) name: String,
+    // Name of the package on the registry, differs from `name` when renamed.
+    pub(crate) package: String,
+    pub(crate) kind: DependencyKind,
+    pub(crate) optional: bool,
+}
+
+impl ManifestDependency {
+    pub(crate) fn crate_name(&self) -> String {
+        self.name.replace('-', "_")
+    }
+}
+
+pub(crate) fn read_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<toml::Table> {
+    Ok(std::fs::read_to_string(manifest_path)?.parse()?)
+}
+
+pub(crate) fn manifest_dependencies(manifest: &toml::Table) -> Vec<ManifestDependency> {
+    let mut tables: Vec<&toml::Table> = vec![manifest];
+    // e.g. `[target.'cfg(unix)'.dependencies]`
+    if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+        tables.extend(targets.values().filter_map(toml::Value::as_table));
+    }
+
+    let mut dependencies = Vec::new();
+
+    for table in tables {
+        for (key, kind) in [
+            ("dependencies", DependencyKind::Normal),
+            ("dev-dependencies", DependencyKind::Dev),
+            ("build-dependencies", DependencyKind::Build),
+        ] {
+            let Some(declared) = table.get(key).and_then(toml::Value::as_table) else {
+                continue;
+            };
+
+            for (name, dependency) in declared {
+                dependencies.push(ManifestDependency {
+                    name: name.clone(),
+                    package: dependency
+                        .get("package")
+                        .and_then(toml::Value::as_str)
+                        .unwrap_or(name)
+                        .to_string(),
+                    kind,
+                    optional: dependency
+                        .get("optional")
+                        .and_then(toml::Value::as_bool)
+                        .unwrap_or(false),
+                });
+            }
+        }
+    }
+
+    dependencies
+}
+
+// Versions of each package in the Cargo.lock next to the manifest, or in that of the workspace
+// root above it. Empty when there is none.
+pub(crate) fn locked_versions<P: AsRef<Path>>(manifest_path: P) -> BTreeMap<String, Vec<String>> {
+    let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    let Some(lock) = manifest_path
+        .as_ref()
+        .ancestors()
+        .skip(1)
+        .map(|directory| directory.join("Cargo.lock"))
+        .find(|lock_path| lock_path.is_file())
+        .and_then(|lock_path| read_manifest(lock_path).ok())
+    else {
+        return versions;
+    };
+
+    for package in lock
+        .get("package")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let (Some(name), Some(version)) = (
+            package.get("name").and_then(toml::Value::as_str),
+            package.get("version").and_then(toml::Value::as_str),
+        ) {
+            versions
+                .entry(name.to_string())
+                .or_default()
+                .push(version.to_string());
+        }
+    }
+
+    versions
+}
@@ -0,0 +1,235 @@
+use crate::{
+    models::{CodeElement, CodeElementID, CodeFile, ItemKind},
+    source::SourceProvider,
+    targets::package_root,
+};
+
+use std::path::Path;
+
+// Splits a Markdown file into sections, one for each ATX heading (plus the text preceding the
+// first heading, if any). The location of a section is made of the file path, relative to the
+// closest directory with a manifest, and of the headings of its parent sections.
+pub(crate) fn parse_markdown<S: SourceProvider + ?Sized, P: AsRef<Path>>(
+    source_provider: &S,
+    file_path: P,
+    code: &str,
+) -> CodeFile<P> {
+    let path = file_path.as_ref();
+    // Only the file name is kept outside of packages.
+    let relative_path = package_root(source_provider, path)
+        .and_then(|package_root| path.strip_prefix(package_root).ok())
+        .unwrap_or_else(|| Path::new(path.file_name().unwrap_or_default()))
+        .to_path_buf();
+    let file_location = format!(
+        "doc :: {}",
+        relative_path
+            .with_extension("")
+            .iter()
+            .filter_map(|component| component.to_str())
+            .filter(|component| ![".", ".."].contains(component))
+            .collect::<Vec<&str>>()
+            .join(" :: ")
+    );
+    let file_stem = relative_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+    // Level, ident, location, first line, lines.
+    let mut sections: Vec<(usize, String, String, usize, Vec<&str>)> = Vec::new();
+    // Level and ident of the open headings.
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (index, line) in code.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some(open_fence) = fence {
+            if trimmed.starts_with(open_fence) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some((level, heading)) = parse_heading(line) {
+            while parents
+                .last()
+                .is_some_and(|(parent_level, _)| *parent_level >= level)
+            {
+                parents.pop();
+            }
+
+            let location = std::iter::once(file_location.as_str())
+                .chain(parents.iter().map(|(_, parent)| parent.as_str()))
+                .collect::<Vec<&str>>()
+                .join(" :: ");
+
+            sections.push((level, heading.clone(), location, index + 1, vec![line]));
+            parents.push((level, heading));
+            continue;
+        }
+
+        match sections.last_mut() {
+            Some(section) => section.4.push(line),
+            None if !line.trim().is_empty() => sections.push((
+                0,
+                file_stem.clone(),
+                file_location.clone(),
+                index + 1,
+                vec![line],
+            )),
+            None => (),
+        }
+    }
+
+    let mut elements: Vec<CodeElement> = sections
+        .iter()
+        .map(|(_, ident, location, line_start, lines)| CodeElement {
+            code_element_id: CodeElementID::new(
+                ident.clone(),
+                ItemKind::DocSection,
+                location.clone(),
+            ),
+            code: lines.join("\n").trim_end().to_string(),
+            line_start: *line_start,
+            imports: Vec::new(),
+            children: Vec::new(),
+            dependencies: Vec::new(),
+            implementors: Vec::new(),
+            span: None,
+            metrics: None,
+            failure_analysis: None,
+            unsafe_sites: Vec::new(),
+            features: Vec::new(),
+        })
+        .collect();
+
+    // Subsections are children of the closest preceding section with a lower level.
+    for index in 1..sections.len() {
+        let level = sections[index].0;
+
+        if let Some(parent_index) = (0..index).rev().find(|parent| sections[*parent].0 < level) {
+            let child = elements[index].code_element_id.clone();
+            elements[parent_index].children.push(child);
+        }
+    }
+
+    CodeFile {
+        path: file_path,
+        elements,
+    }
+}
+
+// Adds each documentation section to the implementors of the code elements it mentions between
+// backticks, dependencies of the sections are derived from implementors afterwards.
+pub(crate) fn add_doc_links(code_elements: &mut [&mut CodeElement]) {
+    let mut links = Vec::new();
+
+    for section in code_elements
+        .iter()
+        .filter(|code_element| code_element.code_element_id.kind == ItemKind::DocSection)
+    {
+        let references = backtick_references(&section.code);
+
+        for code_element in code_elements.iter().filter(|code_element| {
+            ![ItemKind::DocSection, ItemKind::Impl, ItemKind::Verbatim]
+                .contains(&code_element.code_element_id.kind)
+        }) {
+            if references
+                .iter()
+                .any(|reference| reference_matches(reference, &code_element.code_element_id))
+            {
+                links.push((
+                    section.code_element_id.clone(),
+                    code_element.code_element_id.clone(),
+                ));
+            }
+        }
+    }
+
+    for (section_id, code_element_id) in links {
+        for code_element in code_elements.iter_mut() {
+            if code_element.code_element_id == code_element_id {
+                code_element.implementors.push(section_id.clone());
+            }
+        }
+    }
+}
+
+// Content of inline code spans, e.g. `parse_file` or `models::CodeElement`, outside of code blocks.
+pub(crate) fn backtick_references(text: &str) -> Vec<String> {
+    let mut references = Vec::new();
+    let mut fenced = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+            continue;
+        }
+        if fenced {
+            continue;
+        }
+
+        let mut parts = line.split('`');
+        parts.next();
+        while let (Some(reference), Some(_)) = (parts.next(), parts.next()) {
+            if !reference.trim().is_empty() {
+                references.push(reference.trim().to_string());
+            }
+        }
+    }
+
+    references
+}
+
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    // Up to three spaces of indentation are allowed before a heading.
+    if line.len() - line.trim_start_matches(' ').len() > 3 {
+        return None;
+    }
+
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let heading = rest.trim().trim_end_matches('#').trim_end().to_string();
+
+    if heading.is_empty() {
+        None
+    } else {
+        Some((level, heading))
+    }
+}
+
+// A reference matches when its last path segment is the element ident and the preceding segments,
+// if any, are part of the element location, `Type` standing for the `impl_Type` block.
+pub(crate) fn reference_matches(reference: &str, code_element_id: &CodeElementID) -> bool {
+    let reference = reference
+        .trim_end_matches("()")
+        .trim_end_matches('!')
+        .replace(' ', "");
+    let mut segments: Vec<&str> = reference.split("::").collect();
+
+    let Some(ident) = segments.pop() else {
+        return false;
+    };
+    if ident != code_element_id.ident {
+        return false;
+    }
+
+    let location = code_element_id.location.replace(' ', "");
+    segments
+        .iter()
+        .filter(|segment| !["crate", "self", "super"].contains(segment))
+        .all(|segment| {
+            location
+                .split("::")
+                .any(|part| part == *segment || part.strip_prefix("impl_") == Some(*segment))
+        })
+}
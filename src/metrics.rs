@@ -0,0 +1,220 @@
+use crate::models::{CodeElement, CodeFile, CodeMetrics, ItemKind, Metric, MetricsReportEntry};
+
+use syn::visit::{self, Visit};
+
+// Metrics of the elements of the files, modules and impl blocks excluded, sorted by the metric
+// in descending order.
+pub fn metrics_report<P: AsRef<std::path::Path>>(
+    code_files: &[CodeFile<P>],
+    sort_by: Metric,
+) -> Vec<MetricsReportEntry> {
+    let mut entries: Vec<MetricsReportEntry> = code_files
+        .iter()
+        .flat_map(|code_file| &code_file.elements)
+        .filter(|code_element| {
+            ![ItemKind::Impl, ItemKind::Mod].contains(&code_element.code_element_id.kind)
+        })
+        .filter_map(|code_element| {
+            code_element
+                .metrics
+                .as_ref()
+                .map(|metrics| MetricsReportEntry {
+                    code_element_id: code_element.code_element_id.clone(),
+                    metrics: metrics.clone(),
+                })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        b.metrics
+            .get(sort_by)
+            .cmp(&a.metrics.get(sort_by))
+            .then_with(|| a.code_element_id.cmp(&b.code_element_id))
+    });
+
+    entries
+}
+
+pub fn metrics_report_to_markdown(entries: &[MetricsReportEntry]) -> String {
+    let mut markdown = String::from(
+        "| Element | Kind | Location | LOC | Complexity | Nesting | unwrap/expect | unsafe | Parameters |\n\
+         |---|---|---|---|---|---|---|---|---|\n",
+    );
+
+    for entry in entries {
+        let metrics = &entry.metrics;
+        markdown += &format!(
+            "| `{}` | {} | `{}` | {} | {} | {} | {} | {} | {} |\n",
+            entry.code_element_id.ident,
+            entry.code_element_id.kind,
+            entry.code_element_id.location,
+            metrics.lines_of_code,
+            metrics.cyclomatic_complexity,
+            metrics.nesting_depth,
+            metrics.unwrap_calls,
+            metrics.unsafe_blocks,
+            metrics.parameters,
+        );
+    }
+
+    markdown
+}
+
+pub(crate) fn add_metrics(code_elements: &mut [CodeElement]) {
+    for code_element in code_elements {
+        code_element.metrics = code_metrics(&code_element.code);
+    }
+}
+
+// None when the code is not valid Rust on its own, e.g. associated items without a body.
+pub(crate) fn code_metrics(code: &str) -> Option<CodeMetrics> {
+    let mut visitor = MetricsVisitor::default();
+
+    let parameters = if let Ok(file) = syn::parse_str::<syn::File>(code) {
+        visitor.visit_file(&file);
+
+        match file.items.as_slice() {
+            [syn::Item::Fn(item_fn)] => item_fn.sig.inputs.len(),
+            _ => 0,
+        }
+    } else if let Ok(trait_item_fn) = syn::parse_str::<syn::TraitItemFn>(code) {
+        visitor.visit_trait_item_fn(&trait_item_fn);
+        trait_item_fn.sig.inputs.len()
+    } else {
+        return None;
+    };
+
+    Some(CodeMetrics {
+        lines_of_code: lines_of_code(code),
+        cyclomatic_complexity: visitor.complexity,
+        nesting_depth: visitor.max_depth,
+        unwrap_calls: visitor.unwrap_calls,
+        unsafe_blocks: visitor.unsafe_blocks,
+        parameters,
+    })
+}
+
+fn lines_of_code(code: &str) -> usize {
+    let mut in_block_comment = false;
+
+    code.lines()
+        .filter(|line| {
+            let line = line.trim();
+
+            if in_block_comment {
+                in_block_comment = !line.contains("*/");
+                return false;
+            }
+            if line.starts_with("/*") {
+                in_block_comment = !line.contains("*/");
+                return false;
+            }
+
+            !line.is_empty() && !line.starts_with("//")
+        })
+        .count()
+}
+
+// Every function adds one to the complexity, as every branch does.
+#[derive(Default)]
+struct MetricsVisitor {
+    complexity: usize,
+    depth: usize,
+    max_depth: usize,
+    unwrap_calls: usize,
+    unsafe_blocks: usize,
+}
+
+impl MetricsVisitor {
+    fn nested<F: FnOnce(&mut Self)>(&mut self, visit: F) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        visit(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for MetricsVisitor {
+    fn visit_arm(&mut self, node: &'ast syn::Arm) {
+        if node.guard.is_some() {
+            self.complexity += 1;
+        }
+        visit::visit_arm(self, node);
+    }
+
+    fn visit_expr_binary(&mut self, node: &'ast syn::ExprBinary) {
+        if matches!(node.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) {
+            self.complexity += 1;
+        }
+        visit::visit_expr_binary(self, node);
+    }
+
+    fn visit_expr_closure(&mut self, node: &'ast syn::ExprClosure) {
+        self.nested(|visitor| visit::visit_expr_closure(visitor, node));
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast syn::ExprForLoop) {
+        self.complexity += 1;
+        self.nested(|visitor| visit::visit_expr_for_loop(visitor, node));
+    }
+
+    // `else if` chains stay at the depth of the first `if`.
+    fn visit_expr_if(&mut self, node: &'ast syn::ExprIf) {
+        self.complexity += 1;
+        self.nested(|visitor| {
+            visitor.visit_expr(&node.cond);
+            visitor.visit_block(&node.then_branch);
+        });
+
+        if let Some((_, else_branch)) = &node.else_branch {
+            match else_branch.as_ref() {
+                syn::Expr::If(else_if) => self.visit_expr_if(else_if),
+                else_branch => self.nested(|visitor| visitor.visit_expr(else_branch)),
+            }
+        }
+    }
+
+    fn visit_expr_loop(&mut self, node: &'ast syn::ExprLoop) {
+        self.complexity += 1;
+        self.nested(|visitor| visit::visit_expr_loop(visitor, node));
+    }
+
+    fn visit_expr_match(&mut self, node: &'ast syn::ExprMatch) {
+        self.complexity += node.arms.len().saturating_sub(1);
+        self.nested(|visitor| visit::visit_expr_match(visitor, node));
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if ["unwrap", "expect"].contains(&node.method.to_string().as_str()) {
+            self.unwrap_calls += 1;
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        self.unsafe_blocks += 1;
+        self.nested(|visitor| visit::visit_expr_unsafe(visitor, node));
+    }
+
+    fn visit_expr_while(&mut self, node: &'ast syn::ExprWhile) {
+        self.complexity += 1;
+        self.nested(|visitor| visit::visit_expr_while(visitor, node));
+    }
+
+    // Arguments of function-like macros such as `format!` or `assert_eq!` are plain tokens.
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Ok(args) = node.parse_body_with(
+            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+        ) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+        visit::visit_macro(self, node);
+    }
+
+    fn visit_signature(&mut self, node: &'ast syn::Signature) {
+        self.complexity += 1;
+        visit::visit_signature(self, node);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::utils::{deserialize_bool_from_str, deserialize_tuple_from_list, get_type_ident};
+use crate::utils::{
+    default_example_max_retries, deserialize_bool_from_str, deserialize_tuple_from_list, impl_ident,
+};
+use anyhow::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::hash_map::DefaultHasher,
@@ -15,14 +18,98 @@ pub struct CodeElement {
     pub code: String,
     #[serde(skip)]
     pub line_start: usize,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub imports: Vec<String>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<CodeElementID>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub dependencies: Vec<CodeElementID>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub implementors: Vec<CodeElementID>,
+    // Set when `code` has been stripped to avoid duplicating the source file content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<CodeSpan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<CodeMetrics>,
+    // Set for functions, ignoring what happens inside the called ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_analysis: Option<FailureAnalysis>,
+    // Unsafe declaration of the element, and unsafe blocks for functions, consts and statics.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsafe_sites: Vec<UnsafeSite>,
+    // Cargo features the element is gated on through `cfg` attributes, its own or those of the
+    // enclosing modules and impl blocks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+impl CodeElement {
+    // Restores `code` from the source of the file the element belongs to, if it was stripped.
+    // Fails for spans ending before they start, e.g. in a hand-edited JSON Lines file.
+    pub fn resolve_code(&mut self, source: &str) -> Result<()> {
+        if let Some(span) = &self.span {
+            let lines = span
+                .line_end
+                .checked_sub(span.line_start)
+                .and_then(|lines| lines.checked_add(1))
+                .ok_or_else(|| {
+                    Error::msg(format!(
+                        "Span of {} :: {} ends at line {} before starting at line {}",
+                        self.code_element_id.location,
+                        self.code_element_id.ident,
+                        span.line_end,
+                        span.line_start
+                    ))
+                })?;
+
+            self.code = source
+                .lines()
+                .skip(span.line_start.saturating_sub(1))
+                .take(lines)
+                .collect::<Vec<&str>>()
+                .join("\n");
+        }
+
+        Ok(())
+    }
+
+    // Replaces `code` with the lines it occupies inside its source file.
+    pub(crate) fn strip_code(&mut self, line_start: usize) {
+        if self.span.is_none() {
+            let line_end = line_start + self.code.lines().count().saturating_sub(1);
+            self.span = Some(CodeSpan {
+                line_start,
+                line_end,
+            });
+            self.code = String::new();
+        }
+    }
+}
+
+// Computed from the syn AST, modules and impl blocks sum up their content.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CodeMetrics {
+    // Lines that are neither blank nor comments.
+    pub lines_of_code: usize,
+    pub cyclomatic_complexity: usize,
+    pub nesting_depth: usize,
+    // `unwrap` and `expect` calls.
+    pub unwrap_calls: usize,
+    pub unsafe_blocks: usize,
+    pub parameters: usize,
+}
+
+impl CodeMetrics {
+    pub fn get(&self, metric: Metric) -> usize {
+        match metric {
+            Metric::CyclomaticComplexity => self.cyclomatic_complexity,
+            Metric::LinesOfCode => self.lines_of_code,
+            Metric::NestingDepth => self.nesting_depth,
+            Metric::Parameters => self.parameters,
+            Metric::UnsafeBlocks => self.unsafe_blocks,
+            Metric::UnwrapCalls => self.unwrap_calls,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -46,6 +133,77 @@ impl CodeElementID {
             location,
         }
     }
+
+    // The target is encoded in the first segment of the location.
+    pub fn role(&self) -> TargetRole {
+        match self.location.split(" :: ").next() {
+            Some("example") => TargetRole::Example,
+            Some("test") => TargetRole::Test,
+            Some("bench") => TargetRole::Bench,
+            Some("build_script") => TargetRole::BuildScript,
+            Some("dependency") => TargetRole::Dependency,
+            Some("doc") => TargetRole::Doc,
+            _ => TargetRole::Library,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiChange {
+    pub path: String,
+    pub kind: ApiChangeKind,
+    pub breaking: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_signature: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiChangeKind {
+    Added,
+    Removed,
+    RequiredTraitItemAdded,
+    SignatureChanged,
+}
+
+impl std::fmt::Display for ApiChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ApiChangeKind::Added => "added",
+            ApiChangeKind::Removed => "removed",
+            ApiChangeKind::RequiredTraitItemAdded => "required trait item added",
+            ApiChangeKind::SignatureChanged => "signature changed",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiItem {
+    pub path: String,
+    pub kind: ItemKind,
+    pub signature: String,
+    // True for trait items without a default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    // Ident of the implementing type, for impl blocks and methods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_type: Option<String>,
+}
+
+// Sorted by path.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ApiSnapshot {
+    pub items: Vec<ApiItem>,
+}
+
+// Lines are 1-based and inclusive.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CodeSpan {
+    pub line_start: usize,
+    pub line_end: usize,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -54,7 +212,208 @@ pub struct CodeFile<P: AsRef<std::path::Path>> {
     pub elements: Vec<CodeElement>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// A dependency declared in Cargo.toml, with the items using it.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CrateUsage {
+    // Name of the crate in code, dashes replaced and renames applied.
+    pub name: String,
+    pub package: String,
+    pub kinds: Vec<DependencyKind>,
+    // File modules importing or referring to the crate.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<CodeElementID>,
+    // Items whose code refers to the crate, by path or through an import.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elements: Vec<CodeElementID>,
+}
+
+// Sorted by crate name.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CrateUsageReport {
+    pub crates: Vec<CrateUsage>,
+    // Declared dependencies no module refers to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unused_dependencies: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyKind {
+    Build,
+    Dev,
+    Normal,
+}
+
+impl std::fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+            DependencyKind::Normal => "normal",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// Runs the examples generated for functions as doctests of the crate, built offline with the local
+// toolchain. Failing examples are sent back to the model with the errors up to `max_retries` times.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExampleValidation {
+    pub manifest_path: std::path::PathBuf,
+    #[serde(default = "default_example_max_retries")]
+    pub max_retries: usize,
+    #[serde(default)]
+    pub on_failure: ExampleFailure,
+}
+
+// What happens to examples that still fail: they are removed, or marked `ignore` so that they are
+// kept for a human to fix without breaking the doctests.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleFailure {
+    Drop,
+    #[default]
+    Mark,
+}
+
+// Replaces the lines `line_start..line_end` of the file, 1-based, with the text, made of whole
+// lines. Insertions have an empty range, starting at the line the text goes before.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DocumentationEdit {
+    pub path: std::path::PathBuf,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub new_text: String,
+}
+
+// What happens to the documentation elements already have when documenting them: documented
+// elements are not sent to the model with `Skip`, their documentation is overwritten with
+// `Replace`, and `Merge` keeps it, adding the sections it lacks, e.g. `# Errors`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExistingDocumentation {
+    Merge,
+    Replace,
+    #[default]
+    Skip,
+}
+
+// Files replacing the default prompt templates, the documentation one being overridden for the
+// kinds of elements in `doc_generation_by_kind`. Placeholders between braces are replaced in each
+// request: `{ident}`, `{kind}`, `{location}`, `{code}`, `{dependencies}`, the documentation of the
+// items the element contains or uses, `{style_guide}` and `{project_description}`. Documentation
+// templates must contain the first four, and the question one `{code}`, the retrieved code. Braces
+// are written `{{` and `}}` to be kept as they are, e.g. in a Rust format string.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PromptTemplates {
+    // System message of the documentation requests, describing the JSON format of the responses,
+    // whose fields must be kept. Only `{project_description}` is replaced in it.
+    pub doc_generation_system: Option<std::path::PathBuf>,
+    pub doc_generation: Option<std::path::PathBuf>,
+    pub doc_generation_by_kind: std::collections::BTreeMap<ItemKind, std::path::PathBuf>,
+    pub user_question: Option<std::path::PathBuf>,
+    // What the project does and for whom, e.g. its README introduction.
+    pub project_description: String,
+    pub style_guide: String,
+}
+
+// Rules of the project documentation, given to the model with the `{style_guide}` of the prompt
+// templates and checked on its responses. Unset rules are not checked.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StyleGuide {
+    // Characters of the first line of the description, at most.
+    pub summary_max_length: Option<usize>,
+    // Of the first word of the description of functions, e.g. `Returns` or `Return`. Words ending
+    // with `s` but not `ss` are taken as in the third person, so imperative verbs such as `Focus`
+    // or `Alias` are reported as not in the imperative mood.
+    pub summary_tense: Option<SummaryTense>,
+    pub required_sections: std::collections::BTreeMap<ItemKind, Vec<DocumentationSection>>,
+    // Words and phrases the documentation must not contain, whatever their case.
+    pub forbidden_words: Vec<String>,
+    // Headings replacing the default ones, e.g. `Errors`.
+    pub heading_names: std::collections::BTreeMap<DocumentationSection, String>,
+    // Lines of code of the examples, at most.
+    pub example_max_lines: Option<usize>,
+    pub on_violation: StyleViolation,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryTense {
+    Imperative,
+    ThirdPerson,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentationSection {
+    Errors,
+    Examples,
+    Panics,
+    Safety,
+}
+
+impl std::fmt::Display for DocumentationSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DocumentationSection::Errors => "Errors",
+            DocumentationSection::Examples => "Examples",
+            DocumentationSection::Panics => "Panics",
+            DocumentationSection::Safety => "Safety",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// What happens to documentation breaking the style guide: the model is asked once to fix it with
+// `Fix`, and the remaining violations are listed in the `style_violations` of the element.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StyleViolation {
+    Fix,
+    #[default]
+    Report,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DiffReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_commit_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_commit_id: Option<String>,
+    pub added: Vec<CodeElementID>,
+    pub removed: Vec<CodeElementID>,
+    pub moved: Vec<MovedCodeElement>,
+    pub modified: Vec<ModifiedCodeElement>,
+}
+
+// The defaults send one request at a time, without rate limits.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DocGenOptions {
+    // Requests in flight at the same time, across all the files.
+    pub max_concurrent_requests: Option<usize>,
+    // At least 1, the generation failing otherwise.
+    pub requests_per_minute: Option<u32>,
+    // Counting the prompt tokens and the expected completion tokens of each request.
+    pub tokens_per_minute: Option<u32>,
+    pub existing_documentation: ExistingDocumentation,
+    // Computes the documentation edits without writing them inside the repository, even when
+    // writing inside it is asked for.
+    pub dry_run: bool,
+    // Unified diff of the documentation edits, to review or apply with `git apply`. Computed
+    // whether or not the documentation is written inside the repository.
+    pub patch_path: Option<std::path::PathBuf>,
+    pub example_validation: Option<ExampleValidation>,
+    // Rewrites the names between backticks into intra-doc links to the parsed elements.
+    pub intra_doc_links: bool,
+    pub prompt_templates: PromptTemplates,
+    pub style_guide: StyleGuide,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DocumentedCodeElement {
     pub ident: String,
     pub kind: String,
@@ -67,10 +426,206 @@ pub struct DocumentedCodeElement {
     pub error_possible: bool,
     pub error_section: String,
     pub example_section: String,
+    #[serde(default)]
+    pub safety_section: String,
     #[serde(deserialize_with = "deserialize_bool_from_str")]
     pub has_fields_or_variants: bool,
     #[serde(deserialize_with = "deserialize_tuple_from_list")]
     pub fields_or_variants_descriptions: Option<Vec<FieldDescription>>,
+    // Names between backticks not linked, as they match no element or several ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unresolved_references: Vec<String>,
+    // Rules of the style guide the documentation breaks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub style_violations: Vec<String>,
+    // Idents of the field and variant descriptions matching none of the element, which are not
+    // inserted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmatched_field_descriptions: Vec<String>,
+}
+
+// `?` operator applied to the result of an expression.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ErrorPropagation {
+    pub line: usize,
+    pub expression: String,
+    // Function or method whose result is propagated, if the expression is a call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callee: Option<String>,
+    // Resolved against the functions of the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callee_return_type: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FailureAnalysis {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panic_sites: Vec<PanicSite>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub error_propagations: Vec<ErrorPropagation>,
+    // The function return type, when it is a `Result` or an `Option`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Feature {
+    pub name: String,
+    // Enabled by the `default` feature, directly or through other features.
+    pub default: bool,
+    // Features and optional dependencies enabled along with this one, as written in Cargo.toml.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enables: Vec<String>,
+    // Gated elements, without those inside gated modules and impl blocks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gated_elements: Vec<CodeElementID>,
+}
+
+// Sorted by feature name.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FeatureMatrix {
+    pub features: Vec<Feature>,
+    // Used in `cfg` attributes but not declared in Cargo.toml.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undeclared_features: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModifiedCodeElement {
+    pub code_element_id: CodeElementID,
+    // Changes to formatting, comments and documentation alone do not count.
+    pub code_changed: bool,
+    // Set only when the signature changed, one of them being None when the code of the other
+    // version has none, e.g. it no longer parses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_dependencies: Vec<CodeElementID>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_dependencies: Vec<CodeElementID>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    CyclomaticComplexity,
+    LinesOfCode,
+    NestingDepth,
+    Parameters,
+    UnsafeBlocks,
+    UnwrapCalls,
+}
+
+impl std::fmt::Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Metric::CyclomaticComplexity => "cyclomatic_complexity",
+            Metric::LinesOfCode => "lines_of_code",
+            Metric::NestingDepth => "nesting_depth",
+            Metric::Parameters => "parameters",
+            Metric::UnsafeBlocks => "unsafe_blocks",
+            Metric::UnwrapCalls => "unwrap_calls",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl std::str::FromStr for Metric {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "").as_str() {
+            "cyclomaticcomplexity" | "complexity" => Ok(Metric::CyclomaticComplexity),
+            "linesofcode" | "loc" => Ok(Metric::LinesOfCode),
+            "nestingdepth" => Ok(Metric::NestingDepth),
+            "parameters" => Ok(Metric::Parameters),
+            "unsafeblocks" => Ok(Metric::UnsafeBlocks),
+            "unwrapcalls" => Ok(Metric::UnwrapCalls),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MetricsReportEntry {
+    pub code_element_id: CodeElementID,
+    pub metrics: CodeMetrics,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MovedCodeElement {
+    pub from: CodeElementID,
+    pub to: CodeElementID,
+    pub code_changed: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PanicSite {
+    pub line: usize,
+    pub kind: PanicSiteKind,
+    pub expression: String,
+}
+
+// Arithmetic sites overflow in debug builds only, divisions by zero panic in release too.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicSiteKind {
+    Arithmetic,
+    Expect,
+    Indexing,
+    Macro,
+    Unwrap,
+}
+
+impl std::fmt::Display for PanicSiteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PanicSiteKind::Arithmetic => "arithmetic",
+            PanicSiteKind::Expect => "expect",
+            PanicSiteKind::Indexing => "indexing",
+            PanicSiteKind::Macro => "macro",
+            PanicSiteKind::Unwrap => "unwrap",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ParsedRevision {
+    pub commit_id: String,
+    pub revision: String,
+    pub code_files: Vec<CodeFile<std::path::PathBuf>>,
+}
+
+// Cargo target a CodeElement belongs to, documentation for Markdown sections, or the library of
+// a dependency for its parsed sources.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetRole {
+    Bench,
+    BuildScript,
+    Dependency,
+    Doc,
+    Example,
+    Library,
+    Test,
+}
+
+impl std::fmt::Display for TargetRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TargetRole::Bench => "bench",
+            TargetRole::BuildScript => "build_script",
+            TargetRole::Dependency => "dependency",
+            TargetRole::Doc => "doc",
+            TargetRole::Example => "example",
+            TargetRole::Library => "library",
+            TargetRole::Test => "test",
+        };
+        write!(f, "{name}")
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -78,6 +633,7 @@ pub struct DocumentedCodeElement {
 pub enum ItemKind {
     All,
     Const,
+    DocSection,
     Enum,
     ExternCrate,
     Fn,
@@ -100,6 +656,7 @@ impl std::fmt::Display for ItemKind {
         let name = match self {
             ItemKind::All => "all",
             ItemKind::Const => "const",
+            ItemKind::DocSection => "doc_section",
             ItemKind::Enum => "enum",
             ItemKind::ExternCrate => "extern_crate",
             ItemKind::Fn => "fn",
@@ -127,6 +684,7 @@ impl std::str::FromStr for ItemKind {
         match s.trim().to_lowercase().as_str() {
             "all" => Ok(ItemKind::All),
             "const" => Ok(ItemKind::Const),
+            "docsection" => Ok(ItemKind::DocSection),
             "enum" => Ok(ItemKind::Enum),
             "externcrate" => Ok(ItemKind::ExternCrate),
             "fn" => Ok(ItemKind::Fn),
@@ -201,8 +759,8 @@ impl SynItem {
             Item::Enum(item) => item.ident.to_string(),
             Item::ExternCrate(item) => item.ident.to_string(),
             Item::Fn(item) => item.sig.ident.to_string(),
-            Item::ForeignMod(item) => String::default(), //TODO: manage this
-            Item::Impl(item) => format!("impl_{}", get_type_ident(item.self_ty.as_ref()).unwrap()),
+            Item::ForeignMod(_) => String::default(), //TODO: manage this
+            Item::Impl(item) => impl_ident(item.self_ty.as_ref()),
             Item::Macro(item) => {
                 if let Some(ident) = &item.ident {
                     ident.to_string()
@@ -223,6 +781,42 @@ impl SynItem {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UnsafeAuditEntry {
+    pub path: std::path::PathBuf,
+    pub code_element_id: CodeElementID,
+    pub unsafe_site: UnsafeSite,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsafeKind {
+    Block,
+    Fn,
+    Impl,
+    Trait,
+}
+
+impl std::fmt::Display for UnsafeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            UnsafeKind::Block => "unsafe block",
+            UnsafeKind::Fn => "unsafe fn",
+            UnsafeKind::Impl => "unsafe impl",
+            UnsafeKind::Trait => "unsafe trait",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UnsafeSite {
+    pub kind: UnsafeKind,
+    pub line: usize,
+    // A `// SAFETY:` comment for blocks and impls, a `# Safety` doc section for fns and traits.
+    pub safety_documented: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserQuestionResponse {
     pub response: String,
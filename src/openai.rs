@@ -1,11 +1,19 @@
 use crate::{
     consts::{
         OPENAI_API_CHAT_COMPLETION_URL, OPENAI_API_EMBEDDING_URL, OPENAI_API_SEED,
-        OPENAI_API_TOP_P, OPENAI_EMBEDDING_MODEL_MAX_TOKENS, SYSTEM_MSG_DOC_GENERATION,
-        SYSTEM_MSG_USER_QUESTION,
+        OPENAI_API_TOP_P, OPENAI_DOC_GENERATION_COMPLETION_TOKENS,
+        OPENAI_DOC_GENERATION_MAX_RELATED_ITEMS, OPENAI_EMBEDDING_MODEL_MAX_TOKENS,
+        SYSTEM_MSG_EXAMPLE_FIX, SYSTEM_MSG_USER_QUESTION,
+    },
+    failures::failure_analysis_to_prompt,
+    models::{
+        CodeElement, CodeElementID, DocumentedCodeElement, ItemKind, PromptTemplates, StyleGuide,
+        TargetRole, UserQuestionResponse,
     },
-    models::{CodeElement, DocumentedCodeElement, ItemKind, UserQuestionResponse},
     qdrant::{retrieve_points_with_filter, retrieve_points_with_vector},
+    rate_limit::RateLimiter,
+    style::style_guide_to_prompt,
+    templates::Templates,
     utils::code_elment_from_scored_point,
 };
 
@@ -65,7 +73,9 @@ pub async fn ask_the_model(
     qdrant_collection_name: &str,
     qdrant_url: &str,
     user_question: &str,
+    prompt_templates: &PromptTemplates,
 ) -> Result<UserQuestionResponse> {
+    let templates = Templates::new(prompt_templates)?;
     let qdrant_client = QdrantClient::from_url(qdrant_url).build()?;
 
     let embedding = create_embedding(embedding_model, openai_api_key, user_question).await?;
@@ -76,18 +86,23 @@ pub async fn ask_the_model(
             .map(code_elment_from_scored_point)
             .collect();
 
-    let mut system_msg = SYSTEM_MSG_USER_QUESTION.to_string();
+    let mut retrieved_code = String::new();
 
     for code_element in retrieved_code_elements {
         expand_context(
             code_element,
             &qdrant_client,
             qdrant_collection_name,
-            &mut system_msg,
+            &mut retrieved_code,
         )
         .await;
     }
 
+    let system_msg = format!(
+        "{}\n{SYSTEM_MSG_USER_QUESTION}",
+        templates.user_question_prompt(&retrieved_code)
+    );
+
     if get_completion_max_tokens(chat_model, &format!("{system_msg}{user_question}"))? < 2000 {
         return Err(Error::msg(
             "The code snippet provided is too long: no room for model response",
@@ -118,7 +133,7 @@ pub async fn ask_the_model(
         let response = response.json::<ChatCompletionObject>().await?;
 
         let user_question_response: UserQuestionResponse =
-            serde_json::from_str(&response.choices.get(0).unwrap().message.content)?;
+            serde_json::from_str(&response.choices.first().unwrap().message.content)?;
 
         Ok(user_question_response)
     } else {
@@ -160,7 +175,7 @@ pub async fn create_embedding(
 
     if response.status().is_success() {
         let response: EmbeddingResponse = response.json().await?;
-        Ok(response.data.get(0).unwrap().embedding.clone())
+        Ok(response.data.first().unwrap().embedding.clone())
     } else {
         Err(Error::msg(format!(
             "Problems with response from OpenAI {embedding_model}: {}",
@@ -169,17 +184,69 @@ pub async fn create_embedding(
     }
 }
 
+// Documentation generated for the items the element contains or uses, summarized to their first
+// paragraph and the errors and panics they document, which callers may propagate.
+fn related_documentation_to_prompt(related_documentation: &[&DocumentedCodeElement]) -> String {
+    let mut prompt = String::from(
+        "Documentation already generated for the items it contains or uses, to stay consistent with:",
+    );
+
+    for documented_code_element in related_documentation
+        .iter()
+        .take(OPENAI_DOC_GENERATION_MAX_RELATED_ITEMS)
+    {
+        let summary = documented_code_element
+            .general_description
+            .split("\n\n")
+            .next()
+            .unwrap_or_default()
+            .trim();
+        prompt += &format!(
+            "\n\n- {} ({}) in {}: {summary}",
+            documented_code_element.ident,
+            documented_code_element.kind,
+            documented_code_element.location
+        );
+
+        if documented_code_element.error_possible
+            && !documented_code_element.error_section.is_empty()
+        {
+            prompt += &format!(
+                "\n  Errors: {}",
+                documented_code_element.error_section.trim()
+            );
+        }
+        if documented_code_element.panic_possible
+            && !documented_code_element.panic_section.is_empty()
+        {
+            prompt += &format!(
+                "\n  Panics: {}",
+                documented_code_element.panic_section.trim()
+            );
+        }
+    }
+
+    prompt
+}
+
 async fn expand_context(
     code_element: CodeElement,
     qdrant_client: &QdrantClient,
     qdrant_collection_name: &str,
-    system_msg: &mut String,
+    retrieved_code: &mut String,
 ) {
-    *system_msg += &format!("\n{}\n", code_element.code);
+    *retrieved_code += &format!("\n{}\n", code_element.code);
+    // Documentation sections mentioning the element, and the examples, tests and benches using
+    // it, are implementors of it.
     for code_element_id in code_element
         .dependencies
         .iter()
         .chain(&code_element.children)
+        .chain(code_element.implementors.iter().filter(|code_element_id| {
+            code_element_id.kind == ItemKind::DocSection
+                || [TargetRole::Bench, TargetRole::Example, TargetRole::Test]
+                    .contains(&code_element_id.role())
+        }))
     {
         let filter = qdrant_client::qdrant::Filter::must([
             qdrant_client::qdrant::Condition::has_id([code_element_id.get_hash()]),
@@ -192,7 +259,7 @@ async fn expand_context(
                 .first()
                 .and_then(|retrieved_point| retrieved_point.payload.get("code"))
             {
-                *system_msg += &format!("\n{code}\n");
+                *retrieved_code += &format!("\n{code}\n");
             }
         }
     }
@@ -201,31 +268,118 @@ async fn expand_context(
 pub async fn generate_documentation(
     chat_model: &str,
     openai_api_key: &str,
-    ident: &str,
-    kind: &ItemKind,
-    location: &str,
-    code: &str,
+    code_element: &CodeElement,
+    related_documentation: &[&DocumentedCodeElement],
+    templates: &Templates,
+    style_guide: &StyleGuide,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<DocumentedCodeElement> {
+    let dependencies = if related_documentation.is_empty() {
+        String::new()
+    } else {
+        related_documentation_to_prompt(related_documentation)
+    };
+    let mut user_msg = templates.doc_generation_prompt(
+        code_element,
+        &dependencies,
+        &style_guide_to_prompt(style_guide, &code_element.code_element_id.kind),
+    );
+
+    if let Some(failure_analysis) = &code_element.failure_analysis {
+        user_msg += &format!("\n\n{}", failure_analysis_to_prompt(failure_analysis));
+    }
+
+    request_documentation(
+        chat_model,
+        openai_api_key,
+        &templates.doc_generation_system_prompt(),
+        &user_msg,
+        rate_limiter,
+    )
+    .await
+}
+
+// Asks the model to rewrite documentation breaking the style guide, given the rules it breaks.
+#[allow(clippy::too_many_arguments)]
+pub async fn fix_documentation_style(
+    chat_model: &str,
+    openai_api_key: &str,
+    code_element: &CodeElement,
+    raw_documented_code_element: &DocumentedCodeElement,
+    style_violations: &[String],
+    templates: &Templates,
+    style_guide: &StyleGuide,
+    rate_limiter: Option<&RateLimiter>,
+) -> Result<DocumentedCodeElement> {
+    let CodeElementID {
+        ident,
+        kind,
+        location,
+    } = &code_element.code_element_id;
+    let code = &code_element.code;
+
+    // Written as the model is asked to respond, with booleans as strings.
+    let mut documentation = serde_json::to_value(raw_documented_code_element)?;
+    for field in ["panic_possible", "error_possible", "has_fields_or_variants"] {
+        documentation[field] = Value::String(documentation[field].to_string());
+    }
+
     let user_msg = format!(
-        "Provide the documentation to insert directly in the code of {ident}, a Rust {kind} whose location is {location}:
-        
-        {code}"
+        "The documentation of {ident}, a Rust {kind} whose location is {location}, breaks the style guide of the project. Provide it again, following the style guide.
+
+        Code of {ident}:
+
+        {code}
+
+        Documentation:
+
+        {documentation}
+
+        Rules broken:
+
+        - {}
+
+        {}",
+        style_violations.join("\n- "),
+        style_guide_to_prompt(style_guide, kind)
     );
 
-    if get_completion_max_tokens(
+    request_documentation(
         chat_model,
-        &format!("{SYSTEM_MSG_DOC_GENERATION}{user_msg}"),
-    )? < 2000
-    {
+        openai_api_key,
+        &templates.doc_generation_system_prompt(),
+        &user_msg,
+        rate_limiter,
+    )
+    .await
+}
+
+async fn request_documentation(
+    chat_model: &str,
+    openai_api_key: &str,
+    system_msg: &str,
+    user_msg: &str,
+    rate_limiter: Option<&RateLimiter>,
+) -> Result<DocumentedCodeElement> {
+    if get_completion_max_tokens(chat_model, &format!("{system_msg}{user_msg}"))? < 2000 {
         return Err(Error::msg(
             "The code snippet provided is too long: no room for model response",
         ));
     }
 
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter
+            .acquire(
+                count_tokens(chat_model, &format!("{system_msg}{user_msg}"))
+                    + OPENAI_DOC_GENERATION_COMPLETION_TOKENS,
+            )
+            .await;
+    }
+
     let request_body = serde_json::json!({
         "model": chat_model,
         "messages": [
-            {"role": "system", "content": SYSTEM_MSG_DOC_GENERATION},
+            {"role": "system", "content": system_msg},
             {"role": "user", "content": user_msg}
         ],
         "response_format": {"type": "json_object"},
@@ -246,7 +400,7 @@ pub async fn generate_documentation(
         let response = response.json::<ChatCompletionObject>().await?;
 
         let mut raw_documented_code_element: DocumentedCodeElement =
-            serde_json::from_str(&response.choices.get(0).unwrap().message.content)?;
+            serde_json::from_str(&response.choices.first().unwrap().message.content)?;
 
         //TODO: improve this
         raw_documented_code_element.kind = raw_documented_code_element.kind.to_lowercase();
@@ -265,3 +419,87 @@ pub async fn generate_documentation(
         )))
     }
 }
+
+// Asks the model to rewrite an example whose doctest failed, given the output of rustdoc.
+pub async fn fix_documentation_example(
+    chat_model: &str,
+    openai_api_key: &str,
+    code_element: &CodeElement,
+    example: &str,
+    doctest_output: &str,
+    rate_limiter: Option<&RateLimiter>,
+) -> Result<String> {
+    let CodeElementID {
+        ident,
+        kind,
+        location,
+    } = &code_element.code_element_id;
+    let code = &code_element.code;
+
+    let user_msg = format!(
+        "The example documenting {ident}, a Rust {kind} whose location is {location}, fails as a doctest.
+
+        Code of {ident}:
+
+        {code}
+
+        Example:
+
+        {example}
+
+        Output of rustdoc:
+
+        {doctest_output}"
+    );
+
+    if get_completion_max_tokens(chat_model, &format!("{SYSTEM_MSG_EXAMPLE_FIX}{user_msg}"))? < 2000
+    {
+        return Err(Error::msg(
+            "The code snippet provided is too long: no room for model response",
+        ));
+    }
+
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter
+            .acquire(
+                count_tokens(chat_model, &format!("{SYSTEM_MSG_EXAMPLE_FIX}{user_msg}"))
+                    + OPENAI_DOC_GENERATION_COMPLETION_TOKENS,
+            )
+            .await;
+    }
+
+    let request_body = serde_json::json!({
+        "model": chat_model,
+        "messages": [
+            {"role": "system", "content": SYSTEM_MSG_EXAMPLE_FIX},
+            {"role": "user", "content": user_msg}
+        ],
+        "response_format": {"type": "json_object"},
+        "seed": OPENAI_API_SEED,
+        "top_p": OPENAI_API_TOP_P,
+    });
+
+    let response = reqwest::Client::new()
+        .post(OPENAI_API_CHAT_COMPLETION_URL)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {openai_api_key}"))
+        .json(&request_body)
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        let response = response.json::<ChatCompletionObject>().await?;
+        let content: Value =
+            serde_json::from_str(&response.choices.first().unwrap().message.content)?;
+
+        content["example_section"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| Error::msg("No example in the response from OpenAI"))
+    } else {
+        Err(Error::msg(format!(
+            "Problems with response from OpenAI {chat_model}: {}",
+            response.text().await?,
+        )))
+    }
+}
@@ -1,11 +1,19 @@
 use crate::{
     doc_gen::pattern_formatter,
-    models::{CodeElement, CodeElementID, CodeFile, ItemKind, SynItem},
-    utils::{get_code_from_nested, get_item_from_nested, get_type_ident},
+    failures::{add_failure_analysis, resolve_error_propagations},
+    features::{add_cfg_features, add_feature_gates, cfg_features},
+    jsonl::{write_jsonl, write_revision_jsonl},
+    markdown::{add_doc_links, parse_markdown},
+    metrics::add_metrics,
+    models::{CodeElement, CodeElementID, CodeFile, ItemKind, ParsedRevision, SynItem},
+    source::{FsSourceProvider, GitSourceProvider, SourceProvider},
+    targets::{add_usage_links, target_location},
+    unsafety::add_unsafe_sites,
+    utils::{get_code_from_nested, get_item_from_nested, impl_ident},
 };
 
 use anyhow::Result;
-use syn::{parse_file as syn_parse_file, Item, __private::ToTokens, spanned::Spanned};
+use syn::{__private::ToTokens, parse_file as syn_parse_file, spanned::Spanned, Item};
 
 //TODO: manage partially qualified
 fn contains_fully_qualified(ident: &str, location: &str, text: &str) -> bool {
@@ -105,12 +113,8 @@ pub(crate) fn retrieve_code_element(
         }
         //TODO: manage ident for trait implementation
         Item::Impl(ref impl_item) => {
-            let impl_location = location.to_string()
-                + " :: "
-                + &format!(
-                    "impl_{}",
-                    get_type_ident(impl_item.self_ty.as_ref()).unwrap()
-                );
+            let impl_location =
+                location.to_string() + " :: " + &impl_ident(impl_item.self_ty.as_ref());
             let mut children = Vec::new();
 
             for nested_item in &impl_item.items {
@@ -139,6 +143,11 @@ pub(crate) fn retrieve_code_element(
                     children: Vec::new(),
                     dependencies: Vec::new(),
                     implementors: Vec::new(),
+                    span: None,
+                    metrics: None,
+                    failure_analysis: None,
+                    unsafe_sites: Vec::new(),
+                    features: Vec::new(),
                 };
 
                 children.push(nested_code_element_id);
@@ -206,6 +215,11 @@ pub(crate) fn retrieve_code_element(
                     children: Vec::new(),
                     dependencies: Vec::new(),
                     implementors: Vec::new(),
+                    span: None,
+                    metrics: None,
+                    failure_analysis: None,
+                    unsafe_sites: Vec::new(),
+                    features: Vec::new(),
                 };
 
                 children.push(nested_code_element_id);
@@ -225,6 +239,11 @@ pub(crate) fn retrieve_code_element(
         children,
         dependencies: Vec::new(),
         implementors: Vec::new(),
+        span: None,
+        metrics: None,
+        failure_analysis: None,
+        unsafe_sites: Vec::new(),
+        features: Vec::new(),
     };
 
     // This is to differentiate between mods defined inside a mod.rs file from those defined inside actual code.
@@ -246,86 +265,191 @@ pub fn parse_repository<P: AsRef<std::path::Path>>(
     repository_path: P,
     write_to_json_path: Option<P>,
 ) -> Result<Vec<CodeFile<std::path::PathBuf>>> {
-    let mut parsed_repository = parsing_step_1(repository_path)?;
+    parse_repository_with_provider(&FsSourceProvider, repository_path, write_to_json_path)
+}
+
+pub fn parse_repository_with_provider<S: SourceProvider + ?Sized, P: AsRef<std::path::Path>>(
+    source_provider: &S,
+    repository_path: P,
+    write_to_json_path: Option<P>,
+) -> Result<Vec<CodeFile<std::path::PathBuf>>> {
+    let mut parsed_repository = parsing_step_1(source_provider, repository_path)?;
     parsed_repository = parsing_step_2(parsed_repository);
 
-    //TODO: check file extension.
     if let Some(path) = write_to_json_path {
-        let json_data = serde_json::to_string_pretty(&parsed_repository)?;
-        std::fs::write(path, json_data)?;
+        write_code_files(&parsed_repository, path)?;
     }
 
     Ok(parsed_repository)
 }
 
+// JSON Lines when the extension is `jsonl`, pretty printed JSON otherwise.
+pub(crate) fn write_code_files<P: AsRef<std::path::Path>>(
+    code_files: &[CodeFile<std::path::PathBuf>],
+    path: P,
+) -> Result<()> {
+    if path.as_ref().extension().is_some_and(|ext| ext == "jsonl") {
+        write_jsonl(code_files, path)?;
+    } else {
+        let json_data = serde_json::to_string_pretty(code_files)?;
+        std::fs::write(path, json_data)?;
+    }
+
+    Ok(())
+}
+
+// Reads files from the git object database instead of the working tree. The repository path can
+// be any directory inside the git work tree, only its content is parsed.
+pub fn parse_repository_at_revision<P: AsRef<std::path::Path>>(
+    repository_path: P,
+    revision: &str,
+    write_to_json_path: Option<P>,
+) -> Result<ParsedRevision> {
+    let source_provider = GitSourceProvider::new(&repository_path, revision)?;
+    let code_files = parsing_step_1(&source_provider, &repository_path)?;
+
+    let parsed_revision = ParsedRevision {
+        commit_id: source_provider.commit_id().to_string(),
+        revision: revision.to_string(),
+        code_files: parsing_step_2(code_files),
+    };
+
+    // JSON Lines start with the commit, as a revision record.
+    if let Some(path) = write_to_json_path {
+        if path.as_ref().extension().is_some_and(|ext| ext == "jsonl") {
+            write_revision_jsonl(&parsed_revision, path)?;
+        } else {
+            let json_data = serde_json::to_string_pretty(&parsed_revision)?;
+            std::fs::write(path, json_data)?;
+        }
+    }
+
+    Ok(parsed_revision)
+}
+
 pub fn parse_file<P: AsRef<std::path::Path>>(file_path: P) -> Result<CodeFile<P>> {
-    let mut code_elements = Vec::new();
+    parse_file_with_provider(&FsSourceProvider, file_path)
+}
+
+pub fn parse_file_with_provider<S: SourceProvider + ?Sized, P: AsRef<std::path::Path>>(
+    source_provider: &S,
+    file_path: P,
+) -> Result<CodeFile<P>> {
     let path = file_path.as_ref();
 
     if let Some(extension) = path.extension() {
         if extension == "rs" {
-            let code = std::fs::read_to_string(path)?;
-            let parsed = syn_parse_file(&code)?;
+            let code = source_provider.read_to_string(path)?;
+            return parse_source(source_provider, file_path, code);
+        }
+        if extension == "md" {
+            let code = source_provider.read_to_string(path)?;
+            return Ok(parse_markdown(source_provider, file_path, &code));
+        }
+    }
 
-            // TODO: provide support for non-UTF-8 paths
+    Ok(CodeFile {
+        path: file_path,
+        elements: Vec::new(),
+    })
+}
+
+// Parses the source of a Rust file, the path is only used to compute locations, with the
+// manifests read through the provider.
+pub(crate) fn parse_source<S: SourceProvider + ?Sized, P: AsRef<std::path::Path>>(
+    source_provider: &S,
+    file_path: P,
+    code: String,
+) -> Result<CodeFile<P>> {
+    let path = file_path.as_ref();
+
+    // TODO: provide support for non-UTF-8 paths
+    let location = match target_location(source_provider, path) {
+        Some(location) => location,
+        None => {
             let mut location = path.to_str().unwrap().to_owned();
             if let Some(pos) = location.find("/src/") {
                 location = location[pos + 5..].to_owned();
             }
-            location = format!(
+            format!(
                 "crate :: {}",
                 location.trim_end_matches(".rs").replace('/', " :: ")
-            );
-
-            let imports: Vec<(String, String)> = retrieve_imports(&parsed.items);
-            let mut children = Vec::new();
+            )
+        }
+    };
 
-            for item in &parsed.items {
-                let item_code = get_code_from_nested(&code, item.span(), None);
+    parse_source_at_location(file_path, code, location)
+}
 
-                if let Some(code_element_id) = retrieve_code_element(
-                    item.clone(),
-                    &item_code,
-                    &mut code_elements,
-                    &location,
-                    imports.clone(),
-                ) {
-                    children.push(code_element_id);
-                }
-            }
+// The location is that of the module the file defines, `mod.rs` files included.
+pub(crate) fn parse_source_at_location<P: AsRef<std::path::Path>>(
+    file_path: P,
+    code: String,
+    location: String,
+) -> Result<CodeFile<P>> {
+    let mut code_elements = Vec::new();
+    let path = file_path.as_ref();
+    let parsed = syn_parse_file(&code)?;
+
+    let imports: Vec<(String, String)> = retrieve_imports(&parsed.items);
+    let mut children = Vec::new();
+
+    for item in &parsed.items {
+        let item_code = get_code_from_nested(&code, item.span(), None);
+
+        if let Some(code_element_id) = retrieve_code_element(
+            item.clone(),
+            &item_code,
+            &mut code_elements,
+            &location,
+            imports.clone(),
+        ) {
+            children.push(code_element_id);
+        }
+    }
 
-            if let Some(stem) = path.file_stem() {
-                if let Some(ident) = stem.to_str() {
-                    let (ident, location) = if ident == "mod" {
-                        let ident = path
-                            .parent()
-                            .unwrap()
-                            .file_name()
-                            .unwrap()
-                            .to_str()
-                            .unwrap();
-
-                        let location = location.replace(" :: mod", "");
-
-                        (ident.to_string(), location)
-                    } else {
-                        (ident.to_string(), location)
-                    };
-
-                    code_elements.push(CodeElement {
-                        code_element_id: CodeElementID::new(ident, ItemKind::Mod, location),
-                        code,
-                        line_start: 1,
-                        imports: imports.into_iter().map(|import| import.0).collect(),
-                        children,
-                        dependencies: Vec::new(),
-                        implementors: Vec::new(),
-                    });
-                }
-            }
+    // Modules have no unsafe sites of their own.
+    add_unsafe_sites(&mut code_elements, &code);
+    add_cfg_features(&mut code_elements);
+
+    if let Some(stem) = path.file_stem() {
+        if let Some(ident) = stem.to_str() {
+            let (ident, location) = if ident == "mod" {
+                let ident = path
+                    .parent()
+                    .unwrap()
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap();
+
+                let location = location.replace(" :: mod", "");
+
+                (ident.to_string(), location)
+            } else {
+                (ident.to_string(), location)
+            };
+
+            code_elements.push(CodeElement {
+                code_element_id: CodeElementID::new(ident, ItemKind::Mod, location),
+                code,
+                line_start: 1,
+                imports: imports.into_iter().map(|import| import.0).collect(),
+                children,
+                dependencies: Vec::new(),
+                implementors: Vec::new(),
+                span: None,
+                metrics: None,
+                failure_analysis: None,
+                unsafe_sites: Vec::new(),
+                features: cfg_features(&parsed.attrs),
+            });
         }
     }
 
+    add_metrics(&mut code_elements);
+    add_failure_analysis(&mut code_elements);
+
     Ok(CodeFile {
         path: file_path,
         elements: code_elements,
@@ -344,26 +468,46 @@ fn retrieve_imports(items: &Vec<syn::Item>) -> Vec<(String, String)> {
     imports
 }
 
-fn parsing_step_1<P: AsRef<std::path::Path>>(
+pub(crate) fn parsing_step_1<S: SourceProvider + ?Sized, P: AsRef<std::path::Path>>(
+    source_provider: &S,
     directory_path: P,
 ) -> Result<Vec<CodeFile<std::path::PathBuf>>> {
     let mut code_files = Vec::new();
-    let entries = std::fs::read_dir(directory_path)?;
 
     // First step initializing CodeElements
-    for entry in entries.flatten() {
-        let path = entry.path();
-
-        if path.is_dir() {
-            code_files.extend(parsing_step_1(path)?);
-        } else if path.is_file() {
-            code_files.push(parse_file(path)?);
+    for path in source_provider.list_files(directory_path.as_ref())? {
+        if let Some(code_file) =
+            parse_repository_file(source_provider, directory_path.as_ref(), path)?
+        {
+            code_files.push(code_file);
         }
     }
+
+    add_feature_gates(&mut code_files);
+
     Ok(code_files)
 }
 
-fn parsing_step_2(
+// None for the files of build outputs.
+pub(crate) fn parse_repository_file<S: SourceProvider + ?Sized>(
+    source_provider: &S,
+    directory_path: &std::path::Path,
+    path: std::path::PathBuf,
+) -> Result<Option<CodeFile<std::path::PathBuf>>> {
+    let relative_path = path
+        .strip_prefix(directory_path)
+        .unwrap_or(&path)
+        .to_path_buf();
+
+    // Build outputs are not part of the repository, modules named `target` are.
+    if relative_path.starts_with("target") {
+        return Ok(None);
+    }
+
+    Ok(Some(parse_file_with_provider(source_provider, path)?))
+}
+
+pub(crate) fn parsing_step_2(
     mut code_files: Vec<CodeFile<std::path::PathBuf>>,
 ) -> Vec<CodeFile<std::path::PathBuf>> {
     let mut total_code_elements: Vec<&mut CodeElement> = code_files
@@ -372,7 +516,10 @@ fn parsing_step_2(
         .collect();
 
     add_implementors(&mut total_code_elements);
+    add_usage_links(&mut total_code_elements);
+    add_doc_links(&mut total_code_elements);
     add_dependencies(&mut total_code_elements);
+    resolve_error_propagations(&mut total_code_elements);
 
     for code_element in total_code_elements {
         code_element.dependencies.sort();
@@ -402,6 +549,8 @@ fn add_dependencies(code_elements: &mut Vec<&mut CodeElement>) {
 
         already_modified.push(code_element_to_modify);
     }
+
+    *code_elements = already_modified;
 }
 
 //TODO: implement different logic for modules, impl blocks, traits.
@@ -410,8 +559,13 @@ fn add_implementors(code_elements: &mut Vec<&mut CodeElement>) {
 
     while let Some(code_element_to_modify) = code_elements.pop() {
         // In implementors I avoid to insert these.
-        if [ItemKind::Impl, ItemKind::Mod, ItemKind::Verbatim]
-            .contains(&code_element_to_modify.code_element_id.kind)
+        if [
+            ItemKind::DocSection,
+            ItemKind::Impl,
+            ItemKind::Mod,
+            ItemKind::Verbatim,
+        ]
+        .contains(&code_element_to_modify.code_element_id.kind)
         {
             already_modified.push(code_element_to_modify);
             continue;
@@ -420,8 +574,13 @@ fn add_implementors(code_elements: &mut Vec<&mut CodeElement>) {
         // Searches for the ident of the CodeElement under investigation into all the
         // other CodeElements.
         for code_element in code_elements.iter().chain(&already_modified) {
-            if [ItemKind::Impl, ItemKind::Mod, ItemKind::Verbatim]
-                .contains(&code_element.code_element_id.kind)
+            if [
+                ItemKind::DocSection,
+                ItemKind::Impl,
+                ItemKind::Mod,
+                ItemKind::Verbatim,
+            ]
+            .contains(&code_element.code_element_id.kind)
             {
                 continue;
             }
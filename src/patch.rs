@@ -0,0 +1,349 @@
+use crate::models::DocumentationEdit;
+
+use std::path::Path;
+
+// Lines of context around the changes of a unified diff hunk.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operation {
+    Delete,
+    Equal,
+    Insert,
+}
+
+// Lines `old_start..old_end` of the old version replaced with lines `new_start..new_end` of the
+// new one, 0-based.
+struct Change {
+    old_start: usize,
+    old_end: usize,
+    new_start: usize,
+    new_end: usize,
+}
+
+// Edits turning the old version of the file into the new one.
+pub(crate) fn line_edits(path: &Path, old: &str, new: &str) -> Vec<DocumentationEdit> {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+
+    changes(&old_lines, &new_lines)
+        .into_iter()
+        .map(|change| DocumentationEdit {
+            path: path.to_path_buf(),
+            line_start: change.old_start + 1,
+            line_end: change.old_end + 1,
+            new_text: new_lines[change.new_start..change.new_end].concat(),
+        })
+        .collect()
+}
+
+// Unified diff of the two versions of the file, empty when they are the same. The label is the
+// path written in the headers, relative to the root of the repository for `git apply`.
+pub(crate) fn unified_diff(label: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let changes = changes(&old_lines, &new_lines);
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut diff = format!("diff --git a/{label} b/{label}\n--- a/{label}\n+++ b/{label}\n");
+
+    // Changes whose contexts overlap or touch are in the same hunk.
+    let mut hunks: Vec<Vec<&Change>> = Vec::new();
+    for change in &changes {
+        match hunks.last_mut() {
+            Some(hunk)
+                if change.old_start
+                    <= hunk.last().map_or(0, |last| last.old_end) + 2 * CONTEXT_LINES =>
+            {
+                hunk.push(change)
+            }
+            _ => hunks.push(vec![change]),
+        }
+    }
+
+    for hunk in hunks {
+        let (first, last) = (hunk[0], hunk[hunk.len() - 1]);
+        let old_start = first.old_start.saturating_sub(CONTEXT_LINES);
+        let new_start = first.new_start - (first.old_start - old_start);
+        let old_end = (last.old_end + CONTEXT_LINES).min(old_lines.len());
+        let new_end = last.new_end + (old_end - last.old_end);
+
+        let range = |start: usize, end: usize| {
+            if start == end {
+                format!("{start},0")
+            } else {
+                format!("{},{}", start + 1, end - start)
+            }
+        };
+        diff += &format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_end),
+            range(new_start, new_end)
+        );
+
+        let mut old_line = old_start;
+        for change in hunk {
+            for line in &old_lines[old_line..change.old_start] {
+                diff += &diff_line(' ', line);
+            }
+            for line in &old_lines[change.old_start..change.old_end] {
+                diff += &diff_line('-', line);
+            }
+            for line in &new_lines[change.new_start..change.new_end] {
+                diff += &diff_line('+', line);
+            }
+            old_line = change.old_end;
+        }
+        for line in &old_lines[old_line..old_end] {
+            diff += &diff_line(' ', line);
+        }
+    }
+
+    diff
+}
+
+// Groups the consecutive deletions and insertions of the shortest edit script.
+fn changes(old_lines: &[&str], new_lines: &[&str]) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+
+    for operation in operations(old_lines, new_lines) {
+        if operation == Operation::Equal {
+            old_line += 1;
+            new_line += 1;
+            continue;
+        }
+
+        if !changes
+            .last()
+            .is_some_and(|change| change.old_end == old_line && change.new_end == new_line)
+        {
+            changes.push(Change {
+                old_start: old_line,
+                old_end: old_line,
+                new_start: new_line,
+                new_end: new_line,
+            });
+        }
+        if operation == Operation::Delete {
+            old_line += 1;
+        } else {
+            new_line += 1;
+        }
+
+        let last = changes.len() - 1;
+        changes[last].old_end = old_line;
+        changes[last].new_end = new_line;
+    }
+
+    changes
+}
+
+fn diff_line(prefix: char, line: &str) -> String {
+    match line.strip_suffix('\n') {
+        Some(line) => format!("{prefix}{line}\n"),
+        None => format!("{prefix}{line}\n\\ No newline at end of file\n"),
+    }
+}
+
+// Myers' algorithm, keeping for each number of differences the furthest reaching paths of the
+// previous one, which only span the diagonals it could reach, to backtrack the edit script.
+fn operations(old_lines: &[&str], new_lines: &[&str]) -> Vec<Operation> {
+    let (old_len, new_len) = (old_lines.len() as isize, new_lines.len() as isize);
+    let offset = old_len + new_len + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for differences in 0..=old_len + new_len {
+        // Diagonals -(differences - 1)..=(differences - 1), reached at the previous step.
+        trace.push(if differences == 0 {
+            Vec::new()
+        } else {
+            furthest[(offset - differences + 1) as usize..(offset + differences) as usize].to_vec()
+        });
+
+        for diagonal in (-differences..=differences).step_by(2) {
+            let index = (diagonal + offset) as usize;
+            let mut old_line = if diagonal == -differences
+                || (diagonal != differences && furthest[index - 1] < furthest[index + 1])
+            {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut new_line = old_line - diagonal;
+
+            while old_line < old_len
+                && new_line < new_len
+                && old_lines[old_line as usize] == new_lines[new_line as usize]
+            {
+                old_line += 1;
+                new_line += 1;
+            }
+            furthest[index] = old_line;
+
+            if old_line >= old_len && new_line >= new_len {
+                break 'search;
+            }
+        }
+    }
+
+    let mut operations = Vec::new();
+    let (mut old_line, mut new_line) = (old_len, new_len);
+
+    for (differences, previous) in trace.iter().enumerate().rev() {
+        let differences = differences as isize;
+        if differences == 0 {
+            operations.extend((0..old_line).map(|_| Operation::Equal));
+            break;
+        }
+
+        let previous_furthest = |diagonal: isize| previous[(diagonal + differences - 1) as usize];
+        let diagonal = old_line - new_line;
+        let previous_diagonal = if diagonal == -differences
+            || (diagonal != differences
+                && previous_furthest(diagonal - 1) < previous_furthest(diagonal + 1))
+        {
+            diagonal + 1
+        } else {
+            diagonal - 1
+        };
+        let previous_old_line = previous_furthest(previous_diagonal);
+        let previous_new_line = previous_old_line - previous_diagonal;
+
+        while old_line > previous_old_line && new_line > previous_new_line {
+            operations.push(Operation::Equal);
+            old_line -= 1;
+            new_line -= 1;
+        }
+        operations.push(if old_line == previous_old_line {
+            Operation::Insert
+        } else {
+            Operation::Delete
+        });
+        (old_line, new_line) = (previous_old_line, previous_new_line);
+    }
+
+    operations.reverse();
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str =
+        "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n";
+
+    // Applies the edits from the last one, so that the lines of the others do not move.
+    fn apply(old: &str, edits: &[DocumentationEdit]) -> String {
+        let mut lines: Vec<String> = old.split_inclusive('\n').map(String::from).collect();
+
+        for edit in edits.iter().rev() {
+            lines.splice(
+                edit.line_start - 1..edit.line_end - 1,
+                edit.new_text.split_inclusive('\n').map(String::from),
+            );
+        }
+
+        lines.concat()
+    }
+
+    #[test]
+    fn same_versions() {
+        assert_eq!(unified_diff("src/lib.rs", "a\nb\n", "a\nb\n"), "");
+        assert!(line_edits(Path::new("src/lib.rs"), "a\nb\n", "a\nb\n").is_empty());
+    }
+
+    #[test]
+    fn empty_file() {
+        assert_eq!(
+            unified_diff("src/lib.rs", "", "a\nb\n"),
+            format!("{HEADER}@@ -0,0 +1,2 @@\n+a\n+b\n")
+        );
+        assert_eq!(
+            unified_diff("src/lib.rs", "a\n", ""),
+            format!("{HEADER}@@ -1,1 +0,0 @@\n-a\n")
+        );
+    }
+
+    #[test]
+    fn insert_at_start() {
+        assert_eq!(
+            unified_diff("src/lib.rs", "a\nb\nc\n", "x\na\nb\nc\n"),
+            format!("{HEADER}@@ -1,3 +1,4 @@\n+x\n a\n b\n c\n")
+        );
+
+        let edits = line_edits(Path::new("src/lib.rs"), "a\nb\nc\n", "x\na\nb\nc\n");
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].line_start, edits[0].line_end), (1, 1));
+        assert_eq!(edits[0].new_text, "x\n");
+    }
+
+    #[test]
+    fn insert_at_end() {
+        assert_eq!(
+            unified_diff("src/lib.rs", "a\nb\nc\n", "a\nb\nc\nx\n"),
+            format!("{HEADER}@@ -1,3 +1,4 @@\n a\n b\n c\n+x\n")
+        );
+
+        let edits = line_edits(Path::new("src/lib.rs"), "a\nb\nc\n", "a\nb\nc\nx\n");
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].line_start, edits[0].line_end), (4, 4));
+        assert_eq!(edits[0].new_text, "x\n");
+    }
+
+    #[test]
+    fn no_final_newline() {
+        assert_eq!(
+            unified_diff("src/lib.rs", "a\nb", "a\nc"),
+            format!(
+                "{HEADER}@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"
+            )
+        );
+        assert_eq!(
+            unified_diff("src/lib.rs", "a", "a\n"),
+            format!("{HEADER}@@ -1,1 +1,1 @@\n-a\n\\ No newline at end of file\n+a\n")
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old: String = (1..=20).map(|line| format!("{line}\n")).collect();
+        let new = old
+            .replace("\n2\n", "\ntwo\n")
+            .replace("\n18\n", "\neighteen\n");
+
+        assert_eq!(
+            unified_diff("src/lib.rs", &old, &new),
+            format!(
+                "{HEADER}@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+eighteen\n 19\n 20\n"
+            )
+        );
+    }
+
+    #[test]
+    fn line_edits_round_trip() {
+        let versions = [
+            "",
+            "a\n",
+            "a\nb\nc\n",
+            "x\na\nb\nc\n",
+            "a\nb\nc\nx\n",
+            "a\nb",
+            "a\nc",
+            "/// Doc.\nfn a() {}\n\nfn b() {}\n",
+            "fn a() {}\n\n/// Doc.\n/// More.\nfn b() {}\n",
+            "b\na\nc\na\nb\n",
+        ];
+
+        for old in versions {
+            for new in versions {
+                let edits = line_edits(Path::new("src/lib.rs"), old, new);
+                assert_eq!(apply(old, &edits), new, "{old:?} -> {new:?}");
+            }
+        }
+    }
+}
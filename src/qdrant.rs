@@ -180,8 +180,10 @@ pub async fn upsert_embedding(
         "code": code_element.code,
         "code_element_id": code_element.code_element_id,
         "dependencies": code_element.dependencies,
+        "features": code_element.features,
         "implementors": code_element.implementors,
         "imports": code_element.imports,
+        "role": code_element.code_element_id.role(),
     })
     .try_into() else {
         return Err(Error::msg("Problems during Qdrant Payload generation"));
@@ -0,0 +1,67 @@
+use anyhow::{Error, Result};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Limits the requests and tokens sent over any minute. Requests wait for the oldest ones to leave
+// the window until both limits are respected, one larger than the tokens limit is sent alone.
+pub struct RateLimiter {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+    sent: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+impl RateLimiter {
+    // Fails on a limit of zero requests per minute, which would never let any request through.
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Result<Self> {
+        if requests_per_minute == Some(0) {
+            return Err(Error::msg(
+                "The requests per minute limit must be at least 1, leave it unset for no limit",
+            ));
+        }
+
+        Ok(Self {
+            requests_per_minute,
+            tokens_per_minute,
+            sent: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub async fn acquire(&self, tokens: u64) {
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().await;
+                let now = Instant::now();
+                while sent
+                    .front()
+                    .is_some_and(|(instant, _)| now.duration_since(*instant) >= WINDOW)
+                {
+                    sent.pop_front();
+                }
+
+                let sent_tokens: u64 = sent.iter().map(|(_, tokens)| tokens).sum();
+                let requests_allowed = self
+                    .requests_per_minute
+                    .is_none_or(|limit| sent.len() < limit as usize);
+                let tokens_allowed = self
+                    .tokens_per_minute
+                    .is_none_or(|limit| sent.is_empty() || sent_tokens + tokens <= limit as u64);
+
+                if requests_allowed && tokens_allowed {
+                    sent.push_back((now, tokens));
+                    return;
+                }
+
+                sent.front()
+                    .map(|(instant, _)| WINDOW.saturating_sub(now.duration_since(*instant)))
+                    .unwrap_or_default()
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use crate::{
+    failures::failure_analysis,
+    features::add_cfg_features,
+    metrics::code_metrics,
+    models::{CodeElement, CodeElementID, CodeFile, CodeSpan, ItemKind},
+    unsafety::unsafe_sites,
+};
+
+use anyhow::{Error, Result};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+struct RustdocCrate<'a> {
+    index: &'a Map<String, Value>,
+    paths: &'a Map<String, Value>,
+    root_file_stem: String,
+}
+
+// Converts the output of `rustdoc --output-format json` into CodeFiles. Types, imports and trait
+// implementations are resolved by the compiler, dependencies come from item signatures.
+// Span file names are relative to the crate root, where sources are read from.
+pub fn parse_rustdoc_json<P: AsRef<Path>, Q: AsRef<Path>>(
+    rustdoc_json_path: P,
+    crate_root: Q,
+) -> Result<Vec<CodeFile<PathBuf>>> {
+    let rustdoc_json: Value = serde_json::from_str(&std::fs::read_to_string(rustdoc_json_path)?)?;
+
+    let (Some(index), Some(paths), Some(root)) = (
+        rustdoc_json.get("index").and_then(Value::as_object),
+        rustdoc_json.get("paths").and_then(Value::as_object),
+        rustdoc_json.get("root").map(id_key),
+    ) else {
+        return Err(Error::msg("Not a rustdoc JSON file"));
+    };
+
+    let root_file_stem = index
+        .get(&root)
+        .and_then(|root_item| root_item.pointer("/span/filename"))
+        .and_then(Value::as_str)
+        .and_then(|filename| Path::new(filename).file_stem())
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("lib")
+        .to_string();
+
+    let rustdoc_crate = RustdocCrate {
+        index,
+        paths,
+        root_file_stem,
+    };
+
+    let code_element_ids = rustdoc_crate.code_element_ids(&root);
+
+    let mut sources: BTreeMap<String, Option<String>> = BTreeMap::new();
+    let mut files: BTreeMap<String, Vec<(bool, usize, &String, CodeElement)>> = BTreeMap::new();
+    let mut dependencies: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for (id, code_element_id) in &code_element_ids {
+        let item = &index[id];
+        let Some(filename) = item.pointer("/span/filename").and_then(Value::as_str) else {
+            continue;
+        };
+        let line_start = span_line(item, "begin");
+        let line_end = span_line(item, "end");
+
+        let mut referenced_ids = BTreeSet::new();
+        if code_element_id.kind != ItemKind::Mod {
+            collect_referenced_ids(&item["inner"], &mut referenced_ids);
+        }
+
+        let imports = referenced_ids
+            .iter()
+            .filter_map(|referenced_id| rustdoc_crate.full_path(referenced_id))
+            .collect();
+
+        dependencies.insert(
+            id.clone(),
+            referenced_ids
+                .into_iter()
+                .filter(|referenced_id| {
+                    referenced_id != id && code_element_ids.contains_key(referenced_id)
+                })
+                .collect(),
+        );
+
+        let children = rustdoc_crate
+            .children(item)
+            .iter()
+            .filter_map(|child_id| code_element_ids.get(child_id).cloned())
+            .collect();
+
+        let source = sources
+            .entry(filename.to_string())
+            .or_insert_with(|| std::fs::read_to_string(crate_root.as_ref().join(filename)).ok());
+
+        let mut code_element = CodeElement {
+            code_element_id: code_element_id.clone(),
+            code: String::new(),
+            line_start,
+            imports,
+            children,
+            dependencies: Vec::new(),
+            implementors: Vec::new(),
+            span: Some(CodeSpan {
+                line_start,
+                line_end,
+            }),
+            metrics: None,
+            failure_analysis: None,
+            unsafe_sites: Vec::new(),
+            features: Vec::new(),
+        };
+
+        if let Some(source) = source {
+            code_element.resolve_code(source)?;
+            code_element.span = None;
+            code_element.metrics = code_metrics(&code_element.code);
+            if code_element.code_element_id.kind == ItemKind::Fn {
+                code_element.failure_analysis =
+                    failure_analysis(&code_element.code, code_element.line_start);
+            }
+            add_cfg_features(std::slice::from_mut(&mut code_element));
+            code_element.unsafe_sites = unsafe_sites(
+                &code_element.code,
+                &code_element.code_element_id.kind,
+                code_element.line_start,
+                source,
+            );
+        }
+
+        // As for parsed files, the module of the file goes last.
+        let is_file_module = code_element_id.kind == ItemKind::Mod && line_start == 1;
+
+        files.entry(filename.to_string()).or_default().push((
+            is_file_module,
+            line_start,
+            id,
+            code_element,
+        ));
+    }
+
+    let mut implementors: BTreeMap<&String, Vec<CodeElementID>> = BTreeMap::new();
+    for (id, dependency_ids) in &dependencies {
+        for dependency_id in dependency_ids {
+            implementors
+                .entry(dependency_id)
+                .or_default()
+                .push(code_element_ids[id].clone());
+        }
+    }
+
+    let mut code_files = Vec::with_capacity(files.len());
+
+    for (filename, mut elements) in files {
+        elements.sort_by_key(|(is_file_module, line_start, _, _)| (*is_file_module, *line_start));
+
+        code_files.push(CodeFile {
+            path: crate_root.as_ref().join(filename),
+            elements: elements
+                .into_iter()
+                .map(|(_, _, id, mut code_element)| {
+                    code_element.dependencies = dependencies[id]
+                        .iter()
+                        .map(|dependency_id| code_element_ids[dependency_id].clone())
+                        .collect();
+                    code_element.implementors = implementors.get(id).cloned().unwrap_or_default();
+
+                    code_element.dependencies.sort();
+                    code_element.dependencies.dedup();
+                    code_element.implementors.sort();
+                    code_element.implementors.dedup();
+
+                    code_element
+                })
+                .collect(),
+        });
+    }
+
+    Ok(code_files)
+}
+
+impl RustdocCrate<'_> {
+    fn children(&self, item: &Value) -> Vec<String> {
+        [
+            "/inner/module/items",
+            "/inner/impl/items",
+            "/inner/trait/items",
+        ]
+        .iter()
+        .filter_map(|pointer| item.pointer(pointer).and_then(Value::as_array))
+        .flatten()
+        .map(id_key)
+        .collect()
+    }
+
+    // Walks the crate from its root, giving each local item with a span its CodeElementID.
+    fn code_element_ids(&self, root: &str) -> BTreeMap<String, CodeElementID> {
+        let mut code_element_ids = BTreeMap::new();
+        let mut to_visit = vec![(root.to_string(), None::<String>)];
+
+        while let Some((id, parent_location)) = to_visit.pop() {
+            if code_element_ids.contains_key(&id) {
+                continue;
+            }
+
+            let Some(item) = self.index.get(&id) else {
+                continue;
+            };
+            if item.get("crate_id").and_then(Value::as_u64) != Some(0)
+                || item.get("span").is_none_or(Value::is_null)
+            {
+                continue;
+            }
+
+            let Some((inner_kind, inner)) = item
+                .get("inner")
+                .and_then(Value::as_object)
+                .and_then(|inner| inner.iter().next())
+            else {
+                continue;
+            };
+            // Re-exports lead to items of modules that are otherwise not documented.
+            if ["import", "use"].contains(&inner_kind.as_str()) {
+                if let Some(target_id) = inner.get("id").filter(|target_id| !target_id.is_null()) {
+                    to_visit.push((id_key(target_id), None));
+                }
+                continue;
+            }
+
+            let Some(kind) = item_kind(inner_kind) else {
+                continue;
+            };
+
+            let (ident, location, children_location) = match kind {
+                ItemKind::Mod => {
+                    // Private modules have no canonical path, their file gives their location.
+                    let location = self
+                        .full_path(&id)
+                        .or_else(|| {
+                            item.pointer("/span/filename")
+                                .and_then(Value::as_str)
+                                .filter(|_| span_line(item, "begin") == 1)
+                                .map(file_location)
+                        })
+                        .unwrap_or_else(|| self.module_location(root));
+                    let ident = if id == root {
+                        self.root_file_stem.clone()
+                    } else {
+                        item_name(item)
+                    };
+                    (ident, location.clone(), location)
+                }
+                // As in the syn front end, trait impls are named after their type only.
+                ItemKind::Impl => {
+                    let ident = format!("impl_{}", type_name(&inner["for"]));
+                    let location = inner
+                        .pointer("/for/resolved_path/id")
+                        .map(id_key)
+                        .and_then(|type_id| self.parent_location(&type_id))
+                        .or(parent_location)
+                        .unwrap_or_else(|| self.module_location(root));
+                    let children_location = format!("{location} :: {ident}");
+                    (ident, location, children_location)
+                }
+                _ => {
+                    let ident = item_name(item);
+                    let location = self
+                        .parent_location(&id)
+                        .or(parent_location)
+                        .unwrap_or_else(|| self.module_location(root));
+                    let children_location = format!("{location} :: {ident}");
+                    (ident, location, children_location)
+                }
+            };
+
+            // Impls are not listed among module items, only by the types they implement.
+            let mut child_ids = self.children(item);
+            for pointer in [
+                "/inner/struct/impls",
+                "/inner/enum/impls",
+                "/inner/union/impls",
+            ] {
+                if let Some(impls) = item.pointer(pointer).and_then(Value::as_array) {
+                    child_ids.extend(impls.iter().map(id_key));
+                }
+            }
+            for child_id in child_ids {
+                to_visit.push((child_id, Some(children_location.clone())));
+            }
+
+            code_element_ids.insert(id, CodeElementID::new(ident, kind, location));
+        }
+
+        code_element_ids
+    }
+
+    fn full_path(&self, id: &str) -> Option<String> {
+        let segments: Vec<&str> = self
+            .paths
+            .get(id)?
+            .get("path")?
+            .as_array()?
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+
+        let is_local = self.paths[id].get("crate_id").and_then(Value::as_u64) == Some(0);
+
+        Some(match (is_local, segments.split_first()) {
+            (true, Some((_, []))) => format!("crate :: {}", self.root_file_stem),
+            (true, Some((_, rest))) => format!("crate :: {}", rest.join(" :: ")),
+            _ => segments.join(" :: "),
+        })
+    }
+
+    fn module_location(&self, id: &str) -> String {
+        self.full_path(id)
+            .unwrap_or_else(|| format!("crate :: {}", self.root_file_stem))
+    }
+
+    // Location of items with a canonical path: the module they are defined in.
+    fn parent_location(&self, id: &str) -> Option<String> {
+        let segments = self.paths.get(id)?.get("path")?.as_array()?;
+
+        match segments.len() {
+            0 | 1 => None,
+            2 => Some(format!("crate :: {}", self.root_file_stem)),
+            len => Some(format!(
+                "crate :: {}",
+                segments[1..len - 1]
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<&str>>()
+                    .join(" :: ")
+            )),
+        }
+    }
+}
+
+// Ids of resolved paths and traits referenced by an item.
+fn collect_referenced_ids(value: &Value, referenced_ids: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(id) = object
+                .get("id")
+                .filter(|_| object.contains_key("path") || object.contains_key("name"))
+            {
+                referenced_ids.insert(id_key(id));
+            }
+            for (key, nested) in object {
+                if !["items", "impls", "implementations", "variants", "fields"]
+                    .contains(&key.as_str())
+                {
+                    collect_referenced_ids(nested, referenced_ids);
+                }
+            }
+        }
+        Value::Array(array) => array
+            .iter()
+            .for_each(|nested| collect_referenced_ids(nested, referenced_ids)),
+        _ => (),
+    }
+}
+
+// Ids are strings in older format versions, integers in newer ones.
+fn id_key(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+// Same convention used when parsing files.
+fn file_location(filename: &str) -> String {
+    let mut location = filename.to_string();
+    if let Some(pos) = location.find("src/") {
+        location = location[pos + 4..].to_owned();
+    }
+    format!(
+        "crate :: {}",
+        location
+            .trim_end_matches(".rs")
+            .trim_end_matches("/mod")
+            .replace('/', " :: ")
+    )
+}
+
+fn item_kind(inner_kind: &str) -> Option<ItemKind> {
+    match inner_kind {
+        "assoc_const" | "constant" => Some(ItemKind::Const),
+        "assoc_type" | "type_alias" | "typedef" => Some(ItemKind::Type),
+        "enum" => Some(ItemKind::Enum),
+        "extern_crate" => Some(ItemKind::ExternCrate),
+        "function" => Some(ItemKind::Fn),
+        "impl" => Some(ItemKind::Impl),
+        "macro" | "proc_macro" => Some(ItemKind::Macro),
+        "module" => Some(ItemKind::Mod),
+        "static" => Some(ItemKind::Static),
+        "struct" => Some(ItemKind::Struct),
+        "trait" => Some(ItemKind::Trait),
+        "trait_alias" => Some(ItemKind::TraitAlias),
+        "union" => Some(ItemKind::Union),
+        _ => None,
+    }
+}
+
+fn item_name(item: &Value) -> String {
+    item.get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn last_segment(path: &Value) -> String {
+    path.get("path")
+        .or_else(|| path.get("name"))
+        .and_then(Value::as_str)
+        .and_then(|path| path.rsplit("::").next())
+        .unwrap_or("_")
+        .to_string()
+}
+
+fn span_line(item: &Value, bound: &str) -> usize {
+    item.pointer(&format!("/span/{bound}/0"))
+        .and_then(Value::as_u64)
+        .unwrap_or(1) as usize
+}
+
+// Name of the type in impl idents, the same as `utils::impl_ident` gives, e.g. `Foo` for `&[Foo]`.
+fn type_name(ty: &Value) -> String {
+    if let Some(resolved_path) = ty.get("resolved_path") {
+        last_segment(resolved_path)
+    } else if let Some(name) = ty
+        .get("primitive")
+        .or_else(|| ty.get("generic"))
+        .and_then(Value::as_str)
+    {
+        name.to_string()
+    } else if let Some(inner) = ty
+        .pointer("/borrowed_ref/type")
+        .or_else(|| ty.pointer("/raw_pointer/type"))
+        .or_else(|| ty.pointer("/array/type"))
+        .or_else(|| ty.get("slice"))
+    {
+        type_name(inner)
+    } else if let Some(types) = ty.get("tuple").and_then(Value::as_array) {
+        format!(
+            "({})",
+            types
+                .iter()
+                .map(type_name)
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    } else {
+        "_".to_string()
+    }
+}
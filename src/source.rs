@@ -0,0 +1,163 @@
+use crate::git;
+
+use anyhow::{Error, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+// Where the parser reads repositories from.
+pub trait SourceProvider {
+    // All the files below the directory, at any depth.
+    fn list_files(&self, directory_path: &Path) -> Result<Vec<PathBuf>>;
+
+    fn read_to_string(&self, file_path: &Path) -> Result<String>;
+
+    // Used to find manifests, e.g. the `Cargo.toml` of the package of a file.
+    fn is_file(&self, file_path: &Path) -> bool {
+        self.read_to_string(file_path).is_ok()
+    }
+}
+
+pub struct FsSourceProvider;
+
+impl SourceProvider for FsSourceProvider {
+    fn list_files(&self, directory_path: &Path) -> Result<Vec<PathBuf>> {
+        let mut file_paths = Vec::new();
+
+        for entry in std::fs::read_dir(directory_path)?.flatten() {
+            let path = entry.path();
+
+            if path.is_dir() {
+                file_paths.extend(self.list_files(&path)?);
+            } else if path.is_file() {
+                file_paths.push(path);
+            }
+        }
+
+        Ok(file_paths)
+    }
+
+    fn read_to_string(&self, file_path: &Path) -> Result<String> {
+        Ok(std::fs::read_to_string(file_path)?)
+    }
+
+    fn is_file(&self, file_path: &Path) -> bool {
+        file_path.is_file()
+    }
+}
+
+// Reads files of a commit from the git object database. Paths are those the files would have in
+// the work tree, the repository path can be any directory inside it. Files outside of the
+// repository path, manifests included, are not found.
+pub struct GitSourceProvider {
+    repository_path: PathBuf,
+    commit_id: String,
+    // Paths relative to the repository path.
+    files: BTreeSet<PathBuf>,
+    file_reader: Mutex<git::FileReader>,
+}
+
+impl GitSourceProvider {
+    pub fn new<P: AsRef<Path>>(repository_path: P, revision: &str) -> Result<Self> {
+        let commit_id = git::resolve_commit(&repository_path, revision)?;
+
+        Ok(Self {
+            files: git::list_files(&repository_path, &commit_id)?
+                .into_iter()
+                .collect(),
+            commit_id,
+            file_reader: Mutex::new(git::FileReader::new(&repository_path)?),
+            repository_path: repository_path.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn commit_id(&self) -> &str {
+        &self.commit_id
+    }
+
+    fn relative_path<'a>(&self, path: &'a Path) -> Result<&'a Path> {
+        path.strip_prefix(&self.repository_path).map_err(|_| {
+            Error::msg(format!(
+                "{} is outside of {}",
+                path.display(),
+                self.repository_path.display()
+            ))
+        })
+    }
+}
+
+impl SourceProvider for GitSourceProvider {
+    fn list_files(&self, directory_path: &Path) -> Result<Vec<PathBuf>> {
+        let relative_directory_path = self.relative_path(directory_path)?;
+
+        Ok(self
+            .files
+            .iter()
+            .filter(|relative_path| relative_path.starts_with(relative_directory_path))
+            .map(|relative_path| self.repository_path.join(relative_path))
+            .collect())
+    }
+
+    fn read_to_string(&self, file_path: &Path) -> Result<String> {
+        self.file_reader
+            .lock()
+            .map_err(|_| Error::msg("git file reader poisoned"))?
+            .read_file(&self.commit_id, self.relative_path(file_path)?)
+    }
+
+    fn is_file(&self, file_path: &Path) -> bool {
+        self.relative_path(file_path)
+            .is_ok_and(|relative_path| self.files.contains(relative_path))
+    }
+}
+
+// Files kept in memory, e.g. extracted from an upload or an archive.
+#[derive(Default)]
+pub struct MemorySourceProvider {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemorySourceProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<P: Into<PathBuf>>(&mut self, file_path: P, content: String) {
+        self.files.insert(file_path.into(), content);
+    }
+}
+
+impl<P: Into<PathBuf>> FromIterator<(P, String)> for MemorySourceProvider {
+    fn from_iter<I: IntoIterator<Item = (P, String)>>(iter: I) -> Self {
+        Self {
+            files: iter
+                .into_iter()
+                .map(|(file_path, content)| (file_path.into(), content))
+                .collect(),
+        }
+    }
+}
+
+impl SourceProvider for MemorySourceProvider {
+    fn list_files(&self, directory_path: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .keys()
+            .filter(|file_path| file_path.starts_with(directory_path))
+            .cloned()
+            .collect())
+    }
+
+    fn read_to_string(&self, file_path: &Path) -> Result<String> {
+        self.files
+            .get(file_path)
+            .cloned()
+            .ok_or_else(|| Error::msg(format!("{} not found", file_path.display())))
+    }
+
+    fn is_file(&self, file_path: &Path) -> bool {
+        self.files.contains_key(file_path)
+    }
+}
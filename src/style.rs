@@ -0,0 +1,220 @@
+use crate::models::{
+    CodeElement, DocumentationSection, DocumentedCodeElement, ItemKind, StyleGuide, SummaryTense,
+};
+
+use regex::Regex;
+
+pub(crate) fn heading(style_guide: &StyleGuide, section: DocumentationSection) -> String {
+    style_guide
+        .heading_names
+        .get(&section)
+        .cloned()
+        .unwrap_or_else(|| section.to_string())
+}
+
+// Sections required for the kind, named as in the documentation responses, e.g. `fn`.
+pub(crate) fn required_sections<'a>(
+    style_guide: &'a StyleGuide,
+    kind: &str,
+) -> &'a [DocumentationSection] {
+    style_guide
+        .required_sections
+        .iter()
+        .find(|(item_kind, _)| item_kind.to_string() == kind)
+        .map_or(&[], |(_, sections)| sections)
+}
+
+// Rules for an element of the kind, as instructions added to the documentation prompt.
+pub(crate) fn style_guide_to_prompt(style_guide: &StyleGuide, kind: &ItemKind) -> String {
+    let mut rules = Vec::new();
+
+    if let Some(summary_max_length) = style_guide.summary_max_length {
+        rules.push(format!(
+            "The first line of the general description is at most {summary_max_length} characters long."
+        ));
+    }
+    if kind == &ItemKind::Fn {
+        match style_guide.summary_tense {
+            Some(SummaryTense::Imperative) => rules.push(
+                "The general description starts with a verb in the imperative mood, e.g. 'Return' rather than 'Returns'."
+                    .to_string(),
+            ),
+            Some(SummaryTense::ThirdPerson) => rules.push(
+                "The general description starts with a verb in the third person, e.g. 'Returns' rather than 'Return'."
+                    .to_string(),
+            ),
+            None => (),
+        }
+    }
+    if let Some(sections) = style_guide.required_sections.get(kind) {
+        let fields: Vec<&str> = sections
+            .iter()
+            .map(|section| section_field(*section))
+            .collect();
+        if !fields.is_empty() {
+            rules.push(format!(
+                "These fields must not be empty: {}.",
+                fields.join(", ")
+            ));
+        }
+    }
+    if !style_guide.forbidden_words.is_empty() {
+        rules.push(format!(
+            "Never use these words: {}.",
+            style_guide.forbidden_words.join(", ")
+        ));
+    }
+    if let Some(example_max_lines) = style_guide.example_max_lines {
+        rules.push(format!(
+            "Examples have at most {example_max_lines} lines of code."
+        ));
+    }
+
+    if rules.is_empty() {
+        return String::new();
+    }
+    format!("Style guide of the project:\n\n- {}", rules.join("\n- "))
+}
+
+// Forbidden words of the style guide with the regexes matching them as whole words, compiled once
+// per run.
+pub(crate) fn forbidden_word_regexes(style_guide: &StyleGuide) -> Vec<(String, Regex)> {
+    style_guide
+        .forbidden_words
+        .iter()
+        .filter_map(|forbidden_word| {
+            let forbidden_word = forbidden_word.trim();
+            Regex::new(&format!(
+                r"(?i)(^|\W){}($|\W)",
+                regex::escape(forbidden_word)
+            ))
+            .ok()
+            .map(|word| (forbidden_word.to_string(), word))
+        })
+        .collect()
+}
+
+// Rules the documentation breaks, the code of the examples aside for the forbidden words.
+pub(crate) fn style_violations(
+    style_guide: &StyleGuide,
+    forbidden_words: &[(String, Regex)],
+    code_element: &CodeElement,
+    raw_documented_code_element: &DocumentedCodeElement,
+) -> Vec<String> {
+    let kind = &code_element.code_element_id.kind;
+    let mut violations = Vec::new();
+
+    let summary = raw_documented_code_element
+        .general_description
+        .trim()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim();
+
+    if let Some(summary_max_length) = style_guide.summary_max_length {
+        let length = summary.chars().count();
+        if length > summary_max_length {
+            violations.push(format!(
+                "Summary line of {length} characters, more than {summary_max_length}"
+            ));
+        }
+    }
+
+    // Verbs in the third person end with `s`, apart from those ending with `ss`, e.g. `Process`.
+    let first_word = summary
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_matches(|c: char| !c.is_alphabetic());
+    if kind == &ItemKind::Fn && !first_word.is_empty() {
+        let third_person = first_word.ends_with('s') && !first_word.ends_with("ss");
+        match style_guide.summary_tense {
+            Some(SummaryTense::Imperative) if third_person => violations.push(format!(
+                "Summary starting with `{first_word}`, not in the imperative mood"
+            )),
+            Some(SummaryTense::ThirdPerson) if !third_person => violations.push(format!(
+                "Summary starting with `{first_word}`, not in the third person"
+            )),
+            _ => (),
+        }
+    }
+
+    for section in style_guide
+        .required_sections
+        .get(kind)
+        .into_iter()
+        .flatten()
+    {
+        if section_text(raw_documented_code_element, *section)
+            .trim()
+            .is_empty()
+        {
+            violations.push(format!(
+                "Missing `# {}` section",
+                heading(style_guide, *section)
+            ));
+        }
+    }
+
+    let texts: Vec<&str> = [
+        raw_documented_code_element.general_description.as_str(),
+        &raw_documented_code_element.panic_section,
+        &raw_documented_code_element.error_section,
+        &raw_documented_code_element.safety_section,
+    ]
+    .into_iter()
+    .chain(
+        raw_documented_code_element
+            .fields_or_variants_descriptions
+            .iter()
+            .flatten()
+            .map(|(_, description)| description.as_str()),
+    )
+    .collect();
+    for (forbidden_word, word) in forbidden_words {
+        if texts.iter().any(|text| word.is_match(text)) {
+            violations.push(format!("Forbidden word `{forbidden_word}`"));
+        }
+    }
+
+    if let Some(example_max_lines) = style_guide.example_max_lines {
+        let lines = raw_documented_code_element
+            .example_section
+            .lines()
+            .filter(|line| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with("```")
+            })
+            .count();
+        if lines > example_max_lines {
+            violations.push(format!(
+                "Example of {lines} lines of code, more than {example_max_lines}"
+            ));
+        }
+    }
+
+    violations
+}
+
+pub(crate) fn section_text(
+    raw_documented_code_element: &DocumentedCodeElement,
+    section: DocumentationSection,
+) -> &str {
+    match section {
+        DocumentationSection::Errors => &raw_documented_code_element.error_section,
+        DocumentationSection::Examples => &raw_documented_code_element.example_section,
+        DocumentationSection::Panics => &raw_documented_code_element.panic_section,
+        DocumentationSection::Safety => &raw_documented_code_element.safety_section,
+    }
+}
+
+// Field of the documentation responses holding the section.
+fn section_field(section: DocumentationSection) -> &'static str {
+    match section {
+        DocumentationSection::Errors => "error_section",
+        DocumentationSection::Examples => "example_section",
+        DocumentationSection::Panics => "panic_section",
+        DocumentationSection::Safety => "safety_section",
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    models::{CodeElement, CodeElementID, ItemKind, TargetRole},
+    source::SourceProvider,
+};
+
+use std::path::Path;
+
+// Location root of the files of examples, integration tests, benches and build scripts, e.g.
+// `test :: integration` for `tests/integration.rs`. None for library sources. The path is matched
+// from the package root, so that the directories the package is in, e.g. `~/tests/repository`,
+// are not mistaken for target directories.
+pub(crate) fn target_location<S: SourceProvider + ?Sized>(
+    source_provider: &S,
+    path: &Path,
+) -> Option<String> {
+    let relative_path = package_root(source_provider, path)
+        .and_then(|package_root| path.strip_prefix(package_root).ok())
+        .unwrap_or(path);
+    let components: Vec<&str> = relative_path
+        .iter()
+        .filter_map(|component| component.to_str())
+        .collect();
+
+    if components.contains(&"src") {
+        return None;
+    }
+
+    if let Some(position) = components
+        .iter()
+        .rposition(|component| ["examples", "tests", "benches"].contains(component))
+    {
+        let root = match components[position] {
+            "examples" => "example",
+            "tests" => "test",
+            _ => "bench",
+        };
+
+        return Some(format!(
+            "{root} :: {}",
+            components[position + 1..]
+                .join(" :: ")
+                .trim_end_matches(".rs")
+        ));
+    }
+
+    (path.file_name()? == "build.rs").then(|| "build_script :: build".to_string())
+}
+
+// Closest directory above the file with a manifest, read through the provider of the file.
+pub(crate) fn package_root<'a, S: SourceProvider + ?Sized>(
+    source_provider: &S,
+    path: &'a Path,
+) -> Option<&'a Path> {
+    path.ancestors()
+        .skip(1)
+        .find(|directory| source_provider.is_file(&directory.join("Cargo.toml")))
+}
+
+// Adds the elements of examples, integration tests and benches to the implementors of the library
+// items they exercise. These targets reach the library through its crate name, which the
+// generic implementors search does not recognize.
+pub(crate) fn add_usage_links(code_elements: &mut [&mut CodeElement]) {
+    let mut links = Vec::new();
+
+    for user in code_elements.iter().filter(|code_element| {
+        [TargetRole::Bench, TargetRole::Example, TargetRole::Test]
+            .contains(&code_element.code_element_id.role())
+            && is_linkable(&code_element.code_element_id)
+    }) {
+        let compact_code: String = user.code.split_whitespace().collect();
+
+        for item in code_elements.iter().filter(|code_element| {
+            code_element.code_element_id.role() == TargetRole::Library
+                && is_linkable(&code_element.code_element_id)
+        }) {
+            if exercises(user, &compact_code, &item.code_element_id) {
+                links.push((user.code_element_id.clone(), item.code_element_id.clone()));
+            }
+        }
+    }
+
+    for (user_id, item_id) in links {
+        for code_element in code_elements.iter_mut() {
+            if code_element.code_element_id == item_id {
+                code_element.implementors.push(user_id.clone());
+            }
+        }
+    }
+}
+
+// A library item is exercised when it is reached through an import or a path, methods when they
+// are called on a type that is itself reached.
+fn exercises(user: &CodeElement, compact_code: &str, item_id: &CodeElementID) -> bool {
+    let mut module_path: Vec<&str> = item_id.location.split(" :: ").skip(1).collect();
+
+    match module_path.last() {
+        Some(segment) if segment.starts_with("impl_") => {
+            let type_ident = segment.trim_start_matches("impl_").to_string();
+            module_path.pop();
+
+            let called = compact_code.contains(&format!(".{}(", item_id.ident))
+                || compact_code.contains(&format!("{type_ident}::{}", item_id.ident));
+
+            called && reaches(user, compact_code, &module_path, &type_ident)
+        }
+        _ => reaches(user, compact_code, &module_path, &item_id.ident),
+    }
+}
+
+fn reaches(user: &CodeElement, compact_code: &str, module_path: &[&str], ident: &str) -> bool {
+    // Items of the crate root are located in `lib`.
+    let module_path = match module_path {
+        ["lib"] => &[],
+        _ => module_path,
+    };
+
+    let item_path = module_path
+        .iter()
+        .chain(std::iter::once(&ident))
+        .copied()
+        .collect::<Vec<&str>>()
+        .join("::");
+
+    if !module_path.is_empty() && compact_code.contains(&item_path) {
+        return true;
+    }
+
+    let mentioned = user
+        .code
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| word == ident);
+
+    // Imports start with the crate name, which is not known here.
+    mentioned
+        && user.imports.iter().any(|import| {
+            let import: String = import
+                .split(" as ")
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect();
+
+            import.split_once("::").is_some_and(|(_, import_path)| {
+                import_path == item_path || import_path == module_path.join("::")
+            })
+        })
+}
+
+fn is_linkable(code_element_id: &CodeElementID) -> bool {
+    ![
+        ItemKind::DocSection,
+        ItemKind::Impl,
+        ItemKind::Mod,
+        ItemKind::Use,
+        ItemKind::Verbatim,
+    ]
+    .contains(&code_element_id.kind)
+}
@@ -0,0 +1,213 @@
+use crate::{
+    consts::{SYSTEM_MSG_DOC_GENERATION, TEMPLATE_DOC_GENERATION, TEMPLATE_USER_QUESTION},
+    models::{CodeElement, ItemKind, PromptTemplates},
+};
+
+use anyhow::{Error, Result};
+use regex::{Captures, Regex};
+use std::{collections::BTreeMap, sync::LazyLock};
+
+const DOC_GENERATION_PLACEHOLDERS: [&str; 7] = [
+    "code",
+    "dependencies",
+    "ident",
+    "kind",
+    "location",
+    "project_description",
+    "style_guide",
+];
+// Values the model copies in its response.
+const DOC_GENERATION_REQUIRED_PLACEHOLDERS: [&str; 4] = ["code", "ident", "kind", "location"];
+const DOC_GENERATION_SYSTEM_PLACEHOLDERS: [&str; 1] = ["project_description"];
+const USER_QUESTION_PLACEHOLDERS: [&str; 2] = ["code", "project_description"];
+const USER_QUESTION_REQUIRED_PLACEHOLDERS: [&str; 1] = ["code"];
+
+// Prompt templates read and validated once, to render the prompt of each request.
+pub struct Templates {
+    doc_generation_system: String,
+    doc_generation: String,
+    doc_generation_by_kind: BTreeMap<ItemKind, String>,
+    user_question: String,
+    project_description: String,
+    style_guide: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates {
+            doc_generation_system: SYSTEM_MSG_DOC_GENERATION.to_string(),
+            doc_generation: TEMPLATE_DOC_GENERATION.to_string(),
+            doc_generation_by_kind: BTreeMap::new(),
+            user_question: TEMPLATE_USER_QUESTION.to_string(),
+            project_description: String::new(),
+            style_guide: String::new(),
+        }
+    }
+}
+
+impl Templates {
+    // Fails when a file cannot be read, lacks a required placeholder or names an unknown one.
+    pub fn new(prompt_templates: &PromptTemplates) -> Result<Self> {
+        let mut templates = Templates {
+            project_description: prompt_templates.project_description.trim().to_string(),
+            style_guide: prompt_templates.style_guide.trim().to_string(),
+            ..Default::default()
+        };
+
+        if let Some(path) = &prompt_templates.doc_generation_system {
+            templates.doc_generation_system =
+                read_template(path, &DOC_GENERATION_SYSTEM_PLACEHOLDERS, &[])?;
+        }
+        if let Some(path) = &prompt_templates.doc_generation {
+            templates.doc_generation = read_template(
+                path,
+                &DOC_GENERATION_PLACEHOLDERS,
+                &DOC_GENERATION_REQUIRED_PLACEHOLDERS,
+            )?;
+        }
+        for (kind, path) in &prompt_templates.doc_generation_by_kind {
+            templates.doc_generation_by_kind.insert(
+                kind.clone(),
+                read_template(
+                    path,
+                    &DOC_GENERATION_PLACEHOLDERS,
+                    &DOC_GENERATION_REQUIRED_PLACEHOLDERS,
+                )?,
+            );
+        }
+        if let Some(path) = &prompt_templates.user_question {
+            templates.user_question = read_template(
+                path,
+                &USER_QUESTION_PLACEHOLDERS,
+                &USER_QUESTION_REQUIRED_PLACEHOLDERS,
+            )?;
+        }
+
+        Ok(templates)
+    }
+
+    // Describes the format of the responses to documentation requests.
+    pub(crate) fn doc_generation_system_prompt(&self) -> String {
+        render(
+            &self.doc_generation_system,
+            &BTreeMap::from([("project_description", self.project_description.as_str())]),
+        )
+    }
+
+    // The rules of the style guide for the element are added to its text, which is appended to
+    // the prompt when the template has no `{style_guide}`, the rules being checked on responses.
+    pub(crate) fn doc_generation_prompt(
+        &self,
+        code_element: &CodeElement,
+        dependencies: &str,
+        style_rules: &str,
+    ) -> String {
+        let code_element_id = &code_element.code_element_id;
+        let style_guide = [self.style_guide.as_str(), style_rules.trim()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n\n");
+        let template = self
+            .doc_generation_by_kind
+            .get(&code_element_id.kind)
+            .unwrap_or(&self.doc_generation);
+
+        let prompt = render(
+            template,
+            &BTreeMap::from([
+                ("code", code_element.code.as_str()),
+                ("dependencies", dependencies),
+                ("ident", code_element_id.ident.as_str()),
+                ("kind", &code_element_id.kind.to_string()),
+                ("location", code_element_id.location.as_str()),
+                ("project_description", self.project_description.as_str()),
+                ("style_guide", style_guide.as_str()),
+            ]),
+        );
+
+        if style_guide.is_empty() || uses_placeholder(template, "style_guide") {
+            return prompt;
+        }
+        format!("{prompt}\n\n{style_guide}")
+    }
+
+    pub(crate) fn user_question_prompt(&self, code: &str) -> String {
+        render(
+            &self.user_question,
+            &BTreeMap::from([
+                ("code", code),
+                ("project_description", self.project_description.as_str()),
+            ]),
+        )
+    }
+}
+
+// Doubled braces, e.g. those of a Rust format string, are escapes for literal ones.
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{|\}\}|\{([a-z_]+)\}").unwrap());
+
+fn uses_placeholder(template: &str, placeholder: &str) -> bool {
+    PLACEHOLDER.captures_iter(template).any(|captures| {
+        captures
+            .get(1)
+            .is_some_and(|name| name.as_str() == placeholder)
+    })
+}
+
+fn read_template(
+    path: &std::path::Path,
+    placeholders: &[&str],
+    required_placeholders: &[&str],
+) -> Result<String> {
+    let template = std::fs::read_to_string(path).map_err(|e| {
+        Error::msg(format!(
+            "Prompt template {} cannot be read: {e}",
+            path.display()
+        ))
+    })?;
+
+    let used_placeholders: Vec<&str> = PLACEHOLDER
+        .captures_iter(&template)
+        .filter_map(|captures| captures.get(1))
+        .map(|placeholder| placeholder.as_str())
+        .collect();
+
+    if let Some(unknown) = used_placeholders
+        .iter()
+        .find(|placeholder| !placeholders.contains(placeholder))
+    {
+        return Err(Error::msg(format!(
+            "Unknown placeholder {{{unknown}}} in prompt template {}, expected one of: {}",
+            path.display(),
+            placeholders.join(", ")
+        )));
+    }
+    let missing: Vec<&str> = required_placeholders
+        .iter()
+        .filter(|placeholder| !used_placeholders.contains(placeholder))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::msg(format!(
+            "Prompt template {} lacks the placeholders: {}",
+            path.display(),
+            missing.join(", ")
+        )));
+    }
+
+    Ok(template)
+}
+
+// Placeholders are replaced in a single pass, braces in the values, e.g. in the code, being kept.
+fn render(template: &str, values: &BTreeMap<&str, &str>) -> String {
+    PLACEHOLDER
+        .replace_all(template, |captures: &Captures| match captures.get(1) {
+            Some(name) => values
+                .get(name.as_str())
+                .map_or_else(|| captures[0].to_string(), |value| value.to_string()),
+            None => captures[0][..1].to_string(),
+        })
+        .trim()
+        .to_string()
+}
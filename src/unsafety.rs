@@ -0,0 +1,231 @@
+use crate::models::{CodeElement, CodeFile, ItemKind, UnsafeAuditEntry, UnsafeKind, UnsafeSite};
+
+use syn::{
+    spanned::Spanned,
+    visit::{self, Visit},
+};
+
+// Every unsafe site of the files, sorted by path and line.
+pub fn unsafe_audit<P: AsRef<std::path::Path>>(
+    code_files: &[CodeFile<P>],
+) -> Vec<UnsafeAuditEntry> {
+    let mut entries: Vec<UnsafeAuditEntry> = code_files
+        .iter()
+        .flat_map(|code_file| {
+            code_file.elements.iter().flat_map(move |code_element| {
+                code_element
+                    .unsafe_sites
+                    .iter()
+                    .map(move |unsafe_site| UnsafeAuditEntry {
+                        path: code_file.path.as_ref().to_path_buf(),
+                        code_element_id: code_element.code_element_id.clone(),
+                        unsafe_site: unsafe_site.clone(),
+                    })
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| (&a.path, a.unsafe_site.line).cmp(&(&b.path, b.unsafe_site.line)));
+
+    entries
+}
+
+pub fn unsafe_audit_to_markdown(entries: &[UnsafeAuditEntry]) -> String {
+    let mut markdown = String::from("# Unsafe code\n");
+
+    if entries.is_empty() {
+        return markdown + "\nNo unsafe code.\n";
+    }
+
+    let undocumented: Vec<&UnsafeAuditEntry> = entries
+        .iter()
+        .filter(|entry| !entry.unsafe_site.safety_documented)
+        .collect();
+
+    if !undocumented.is_empty() {
+        markdown += "\n## Missing safety documentation\n\n";
+        for entry in undocumented {
+            markdown += &format!(
+                "- {}:{} {} in `{}` (`{}`), {}\n",
+                entry.path.display(),
+                entry.unsafe_site.line,
+                entry.unsafe_site.kind,
+                entry.code_element_id.ident,
+                entry.code_element_id.location,
+                match entry.unsafe_site.kind {
+                    UnsafeKind::Block | UnsafeKind::Impl => "no `// SAFETY:` comment",
+                    UnsafeKind::Fn | UnsafeKind::Trait => "no `# Safety` section",
+                }
+            );
+        }
+    }
+
+    markdown += "\n## Inventory\n\n| Location | Kind | Element | Documented |\n|---|---|---|---|\n";
+    for entry in entries {
+        markdown += &format!(
+            "| {}:{} | {} | `{}` in `{}` | {} |\n",
+            entry.path.display(),
+            entry.unsafe_site.line,
+            entry.unsafe_site.kind,
+            entry.code_element_id.ident,
+            entry.code_element_id.location,
+            if entry.unsafe_site.safety_documented {
+                "yes"
+            } else {
+                "no"
+            }
+        );
+    }
+
+    markdown
+}
+
+pub(crate) fn add_unsafe_sites(code_elements: &mut [CodeElement], source: &str) {
+    for code_element in code_elements {
+        code_element.unsafe_sites = unsafe_sites(
+            &code_element.code,
+            &code_element.code_element_id.kind,
+            code_element.line_start,
+            source,
+        );
+    }
+}
+
+// True for unsafe functions and traits, whose documentation needs a `# Safety` section.
+pub(crate) fn is_unsafe_declaration(code_element: &CodeElement) -> bool {
+    code_element
+        .unsafe_sites
+        .iter()
+        .any(|unsafe_site| [UnsafeKind::Fn, UnsafeKind::Trait].contains(&unsafe_site.kind))
+}
+
+// Lines are those of the source file, given the line the element code starts at, as comments
+// preceding an item are outside of its code. Modules and the items of impl blocks and traits are
+// left to their own elements.
+pub(crate) fn unsafe_sites(
+    code: &str,
+    kind: &ItemKind,
+    line_start: usize,
+    source: &str,
+) -> Vec<UnsafeSite> {
+    let mut visitor = UnsafeVisitor {
+        code_lines: code.lines().collect(),
+        source_lines: source.lines().collect(),
+        line_offset: line_start.saturating_sub(1),
+        unsafe_sites: Vec::new(),
+    };
+
+    match (kind, syn::parse_str::<syn::Item>(code)) {
+        (ItemKind::Fn, Ok(syn::Item::Fn(item_fn))) => {
+            if let Some(unsafety) = &item_fn.sig.unsafety {
+                visitor.add_declaration(UnsafeKind::Fn, unsafety.span().start().line);
+            }
+            visitor.visit_block(&item_fn.block);
+        }
+        (ItemKind::Fn, _) => {
+            if let Ok(trait_item_fn) = syn::parse_str::<syn::TraitItemFn>(code) {
+                if let Some(unsafety) = &trait_item_fn.sig.unsafety {
+                    visitor.add_declaration(UnsafeKind::Fn, unsafety.span().start().line);
+                }
+                if let Some(block) = &trait_item_fn.default {
+                    visitor.visit_block(block);
+                }
+            }
+        }
+        (ItemKind::Impl, Ok(syn::Item::Impl(item_impl))) => {
+            if let Some(unsafety) = &item_impl.unsafety {
+                visitor.add_declaration(UnsafeKind::Impl, unsafety.span().start().line);
+            }
+        }
+        (ItemKind::Trait, Ok(syn::Item::Trait(item_trait))) => {
+            if let Some(unsafety) = &item_trait.unsafety {
+                visitor.add_declaration(UnsafeKind::Trait, unsafety.span().start().line);
+            }
+        }
+        (ItemKind::Const, Ok(syn::Item::Const(item_const))) => {
+            visitor.visit_expr(&item_const.expr);
+        }
+        (ItemKind::Static, Ok(syn::Item::Static(item_static))) => {
+            visitor.visit_expr(&item_static.expr);
+        }
+        _ => (),
+    }
+
+    visitor.unsafe_sites
+}
+
+struct UnsafeVisitor<'a> {
+    code_lines: Vec<&'a str>,
+    source_lines: Vec<&'a str>,
+    line_offset: usize,
+    unsafe_sites: Vec<UnsafeSite>,
+}
+
+impl UnsafeVisitor<'_> {
+    fn add_declaration(&mut self, kind: UnsafeKind, line: usize) {
+        let line = line + self.line_offset;
+        let safety_documented = match kind {
+            UnsafeKind::Fn | UnsafeKind::Trait => self.code_lines.iter().any(|code_line| {
+                let code_line = code_line.trim_start();
+                (code_line.starts_with("///") || code_line.starts_with("#[doc"))
+                    && code_line.contains("# Safety")
+            }),
+            UnsafeKind::Block | UnsafeKind::Impl => self.has_safety_comment(line),
+        };
+
+        self.unsafe_sites.push(UnsafeSite {
+            kind,
+            line,
+            safety_documented,
+        });
+    }
+
+    // The comment can precede the `unsafe` keyword on its line, or be in the comment lines right
+    // above it.
+    fn has_safety_comment(&self, line: usize) -> bool {
+        let Some(code_line) = self.source_lines.get(line.saturating_sub(1)) else {
+            return false;
+        };
+        if code_line
+            .split("unsafe")
+            .next()
+            .is_some_and(|before| before.contains("SAFETY:"))
+        {
+            return true;
+        }
+
+        self.source_lines[..line.saturating_sub(1)]
+            .iter()
+            .rev()
+            .map(|code_line| code_line.trim_start())
+            .take_while(|code_line| code_line.starts_with("//") || code_line.starts_with("/*"))
+            .any(|code_line| code_line.contains("SAFETY:"))
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeVisitor<'_> {
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        self.add_declaration(UnsafeKind::Block, node.unsafe_token.span().start().line);
+        visit::visit_expr_unsafe(self, node);
+    }
+
+    // Nested functions are not elements, their blocks still belong to this one.
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        if let Some(unsafety) = &node.sig.unsafety {
+            self.add_declaration(UnsafeKind::Fn, unsafety.span().start().line);
+        }
+        visit::visit_item_fn(self, node);
+    }
+
+    // Unsafe blocks are often written inside macro invocations.
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Ok(args) = node.parse_body_with(
+            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+        ) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+        visit::visit_macro(self, node);
+    }
+}
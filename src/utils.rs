@@ -1,4 +1,7 @@
-use crate::models::{CodeElement, SynItem};
+use crate::{
+    consts::DOC_EXAMPLE_MAX_RETRIES,
+    models::{CodeElement, SynItem},
+};
 
 use proc_macro2::Span;
 use qdrant_client::qdrant::ScoredPoint;
@@ -15,6 +18,10 @@ pub(crate) fn code_elment_from_scored_point(scored_point: &ScoredPoint) -> CodeE
     from_value(Object(json_map)).expect("msg")
 }
 
+pub(crate) fn default_example_max_retries() -> usize {
+    DOC_EXAMPLE_MAX_RETRIES
+}
+
 pub(crate) fn deserialize_bool_from_str<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -112,3 +119,29 @@ pub(crate) fn get_type_ident(ty: &syn::Type) -> Option<syn::Ident> {
     }
     None
 }
+
+// Ident of the elements of impl blocks, for any implementing type, e.g. `impl_Foo` for `&'a Foo`
+// or `impl_(A,B)` for tuples.
+pub(crate) fn impl_ident(self_ty: &syn::Type) -> String {
+    let ty = match self_ty {
+        syn::Type::Array(type_array) => type_array.elem.as_ref(),
+        syn::Type::Group(type_group) => type_group.elem.as_ref(),
+        syn::Type::Paren(type_paren) => type_paren.elem.as_ref(),
+        syn::Type::Ptr(type_ptr) => type_ptr.elem.as_ref(),
+        syn::Type::Reference(type_reference) => type_reference.elem.as_ref(),
+        syn::Type::Slice(type_slice) => type_slice.elem.as_ref(),
+        _ => {
+            return get_type_ident(self_ty).map_or_else(
+                || {
+                    format!(
+                        "impl_{}",
+                        self_ty.to_token_stream().to_string().replace(' ', "")
+                    )
+                },
+                |ident| format!("impl_{ident}"),
+            )
+        }
+    };
+
+    impl_ident(ty)
+}
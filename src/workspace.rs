@@ -0,0 +1,152 @@
+use crate::{
+    linking::{add_linked_implementors, NamespacedItems},
+    manifest::read_manifest,
+    models::{CodeElementID, CodeFile},
+    parsing::{parse_file, parsing_step_1, parsing_step_2, write_code_files},
+    source::FsSourceProvider,
+};
+
+use anyhow::{Error, Result};
+use std::path::{Path, PathBuf};
+
+// Parses every member of the workspace as `parse_repository` would, then resolves the imports of
+// sibling members into dependencies and implementors. Locations are rooted at
+// `workspace :: crate_name` instead of `crate`, and those of targets and documentation include the
+// crate name, e.g. `test :: crate_name :: integration`, as members share module and file names.
+pub fn parse_workspace<P: AsRef<Path>>(
+    workspace_path: P,
+    write_to_json_path: Option<P>,
+) -> Result<Vec<CodeFile<PathBuf>>> {
+    let manifest = read_manifest(workspace_path.as_ref().join("Cargo.toml"))?;
+    let mut code_files = Vec::new();
+
+    for member_path in workspace_members(workspace_path.as_ref(), &manifest)? {
+        let member_manifest = read_manifest(member_path.join("Cargo.toml"))?;
+        let Some(crate_name) = crate_name(&member_manifest) else {
+            continue;
+        };
+
+        // Only the directories of the targets, as members can be nested in one another.
+        let mut member_files = Vec::new();
+        for target_path in ["src", "examples", "tests", "benches"]
+            .iter()
+            .map(|directory| member_path.join(directory))
+            .filter(|target_path| target_path.is_dir())
+        {
+            member_files.extend(parsing_step_1(&FsSourceProvider, target_path)?);
+        }
+        if member_path.join("build.rs").is_file() {
+            member_files.push(parse_file(member_path.join("build.rs"))?);
+        }
+
+        let mut member_files = parsing_step_2(member_files);
+        relocate(&mut member_files, &crate_name);
+        code_files.extend(member_files);
+    }
+
+    let implementors = NamespacedItems::new(&code_files, "workspace").link_imports(&mut code_files);
+    add_linked_implementors(&mut code_files, &implementors);
+
+    if let Some(path) = write_to_json_path {
+        write_code_files(&code_files, path)?;
+    }
+
+    Ok(code_files)
+}
+
+// The library name, which defaults to the package name with dashes replaced.
+fn crate_name(manifest: &toml::Table) -> Option<String> {
+    manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .or_else(|| manifest.get("package")?.get("name"))
+        .and_then(toml::Value::as_str)
+        .map(|name| name.replace('-', "_"))
+}
+
+fn relocate(code_files: &mut [CodeFile<PathBuf>], crate_name: &str) {
+    let relocate_id = |code_element_id: &mut CodeElementID| {
+        code_element_id.location = match code_element_id.location.split_once(" :: ") {
+            Some(("crate", path)) => format!("workspace :: {crate_name} :: {path}"),
+            Some((root, path)) => format!("{root} :: {crate_name} :: {path}"),
+            None => return,
+        };
+    };
+
+    for code_element in code_files
+        .iter_mut()
+        .flat_map(|code_file| &mut code_file.elements)
+    {
+        relocate_id(&mut code_element.code_element_id);
+        code_element
+            .children
+            .iter_mut()
+            .chain(&mut code_element.dependencies)
+            .chain(&mut code_element.implementors)
+            .for_each(relocate_id);
+
+        for import in &mut code_element.imports {
+            if let Some(path) = import.strip_prefix("crate :: ") {
+                *import = format!("workspace :: {crate_name} :: {path}");
+            }
+        }
+    }
+}
+
+// Directories of the members, the workspace root included when it is a package itself. Glob
+// patterns are supported in the last path component, e.g. `crates/*`.
+fn workspace_members(workspace_path: &Path, manifest: &toml::Table) -> Result<Vec<PathBuf>> {
+    let patterns = |key: &str| -> Vec<&str> {
+        manifest
+            .get("workspace")
+            .and_then(|workspace| workspace.get(key))
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .collect()
+    };
+
+    let mut member_paths = Vec::new();
+    if manifest.contains_key("package") {
+        member_paths.push(workspace_path.to_path_buf());
+    }
+
+    for pattern in patterns("members") {
+        let (parent, name_pattern) = pattern.rsplit_once('/').unwrap_or((".", pattern));
+
+        match name_pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                let mut matching: Vec<PathBuf> =
+                    std::fs::read_dir(workspace_path.join(parent))?
+                        .flatten()
+                        .map(|entry| entry.path())
+                        .filter(|path| {
+                            path.join("Cargo.toml").is_file()
+                                && path.file_name().and_then(|name| name.to_str()).is_some_and(
+                                    |name| name.starts_with(prefix) && name.ends_with(suffix),
+                                )
+                        })
+                        .collect();
+                matching.sort();
+                member_paths.extend(matching);
+            }
+            None => member_paths.push(workspace_path.join(pattern)),
+        }
+    }
+
+    let excluded: Vec<PathBuf> = patterns("exclude")
+        .into_iter()
+        .map(|pattern| workspace_path.join(pattern))
+        .collect();
+    member_paths.retain(|member_path| !excluded.contains(member_path));
+
+    if member_paths.is_empty() {
+        return Err(Error::msg(format!(
+            "No package or workspace member in {}",
+            workspace_path.display()
+        )));
+    }
+
+    Ok(member_paths)
+}